@@ -0,0 +1,67 @@
+use crate::ecs::components::blocks::{animate, AnimationTrait, ChestAnimations};
+use crate::ecs::plugins::game::ShikataganaiGameState;
+use crate::ecs::systems::user_interface::chest_inventory::{InventoryItemMovementStatus, InventoryOpened};
+use crate::ecs::systems::user_interface::furnace::FurnaceOpened;
+use bevy::prelude::{Commands, Entity};
+use bevy_renet::renet::RenetClient;
+use bincode::serialize;
+use iyes_loopless::prelude::NextState;
+use shikataganai_common::networking::{ClientChannel, FunctorType, PlayerCommand};
+use shikataganai_common::util::array::DDD;
+
+// What a right click on a block opens, definitions pick one by name
+#[derive(Copy, Clone)]
+pub enum BlockInterface {
+  Chest,
+  Furnace,
+}
+
+impl BlockInterface {
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "chest" => Some(BlockInterface::Chest),
+      "furnace" => Some(BlockInterface::Furnace),
+      _ => None,
+    }
+  }
+
+  pub fn open(&self, entity: Entity, location: DDD, commands: &mut Commands, client: &mut RenetClient) {
+    match self {
+      BlockInterface::Chest => {
+        commands.insert_resource(InventoryOpened(entity));
+        commands.insert_resource(InventoryItemMovementStatus::Nothing);
+        commands.insert_resource(NextState(ShikataganaiGameState::InterfaceOpened));
+        client.send_message(
+          ClientChannel::ClientCommand.id(),
+          serialize(&PlayerCommand::SubscribeFunctor {
+            location,
+            functor: FunctorType::InternalInventory,
+          })
+          .unwrap(),
+        );
+
+        animate(commands, entity, ChestAnimations::Open.get_animation());
+        client.send_message(
+          ClientChannel::ClientCommand.id(),
+          serialize(&PlayerCommand::AnimationStart {
+            location,
+            animation: ChestAnimations::Open.get_animation(),
+          })
+          .unwrap(),
+        );
+      }
+      BlockInterface::Furnace => {
+        commands.insert_resource(FurnaceOpened(entity));
+        commands.insert_resource(InventoryItemMovementStatus::Nothing);
+        commands.insert_resource(NextState(ShikataganaiGameState::InterfaceOpened));
+        // The slots and the fire are separate functors
+        for functor in [FunctorType::InternalInventory, FunctorType::Furnace] {
+          client.send_message(
+            ClientChannel::ClientCommand.id(),
+            serialize(&PlayerCommand::SubscribeFunctor { location, functor }).unwrap(),
+          );
+        }
+      }
+    }
+  }
+}
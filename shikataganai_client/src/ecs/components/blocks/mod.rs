@@ -1,45 +1,28 @@
+use crate::ecs::components::blocks::interfaces::BlockInterface;
 use crate::ecs::plugins::rendering::mesh_pipeline::loader::Meshes;
 use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResource;
 use bevy::utils::hashbrown::HashMap;
 use bevy_renet::renet::RenetClient;
 use num_traits::FloatConst;
 use shikataganai_common::ecs::components::blocks::animation::{Animation, AnimationType};
 use shikataganai_common::ecs::components::blocks::block_id::BlockId;
-use shikataganai_common::ecs::components::blocks::Block;
+use shikataganai_common::ecs::components::blocks::registry::{BlockDef, BlockRegistry, BlockRender};
 use shikataganai_common::util::array::DDD;
+use std::sync::Arc;
+use strum::IntoEnumIterator;
 
-pub mod regular_blocks;
-pub mod regular_meshes;
+pub mod interfaces;
 
 const BLOCK_SPRITE_SHEET_WIDTH: usize = 8;
 
-#[allow(dead_code)]
+// Tile of texture.png, counted row by row
 #[derive(Copy, Clone)]
-pub enum BlockSprite {
-  Empty,
-  Dirt,
-  HalfGrass,
-  Grass,
-  Cobblestone,
-  Wood,
-  Iron,
-  EMPTY0,
-  EMPTY1,
-  EMPTY2,
-  EMPTY3,
-  EMPTY4,
-  EMPTY5,
-  EMPTY6,
-  EMPTY7,
-  EMPTY8,
-  FurnaceFront,
-  FurnaceSide,
-  FurnaceTop,
-}
+pub struct BlockSprite(pub u32);
 
 impl BlockSprite {
   pub const fn into_uv(self) -> ([f32; 2], [f32; 2]) {
-    let i = self as usize;
+    let i = self.0 as usize;
     let x = i % BLOCK_SPRITE_SHEET_WIDTH;
     let y = i / BLOCK_SPRITE_SHEET_WIDTH;
     (
@@ -55,6 +38,21 @@ impl BlockSprite {
   }
 }
 
+const SIDE_NORMALS: [Vec3; 6] = [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z, Vec3::Y, Vec3::NEG_Y];
+
+// Sprites of a turned block, in the same [+x, -x, +z, -z, top, bottom] order
+pub fn oriented_sprites(sprites: [BlockSprite; 6], orientation: Quat) -> [BlockSprite; 6] {
+  if orientation == Quat::IDENTITY {
+    return sprites;
+  }
+  let inverse = orientation.inverse();
+  SIDE_NORMALS.map(|normal| {
+    let normal = inverse * normal;
+    sprites[SIDE_NORMALS.iter().position(|side| side.dot(normal) > 0.5).unwrap()]
+  })
+}
+
+#[derive(Copy, Clone)]
 pub enum Skeletons {
   Chest,
 }
@@ -66,6 +64,13 @@ pub enum ChestSkeleton {
 }
 
 impl Skeletons {
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "Chest" => Some(Skeletons::Chest),
+      _ => None,
+    }
+  }
+
   pub fn to_skeleton_def(&self) -> SkeletonDef {
     match self {
       Skeletons::Chest => SkeletonDef {
@@ -157,6 +162,7 @@ impl AnimationTrait for ChestAnimations {
   }
 }
 
+#[derive(Clone)]
 pub enum BlockRenderInfo {
   Nothing,
   AsBlock([BlockSprite; 6]),
@@ -164,44 +170,74 @@ pub enum BlockRenderInfo {
   AsSkeleton(Skeletons),
 }
 
-pub trait BlockTraitExt {
-  fn render_info(&self) -> BlockRenderInfo;
-  fn right_click_interface(
+// Client side of a block definition
+pub struct BlockExt {
+  render: BlockRenderInfo,
+  interface: Option<BlockInterface>,
+}
+
+impl BlockExt {
+  fn new(definition: &BlockDef) -> Self {
+    let render = match &definition.render {
+      BlockRender::Nothing => Some(BlockRenderInfo::Nothing),
+      BlockRender::Cube { textures } => Some(BlockRenderInfo::AsBlock(textures.map(BlockSprite))),
+      BlockRender::Mesh { mesh } => Meshes::iter()
+        .find(|candidate| format!("{:?}", candidate) == *mesh)
+        .map(BlockRenderInfo::AsMesh),
+      BlockRender::Skeleton { skeleton } => Skeletons::from_name(skeleton).map(BlockRenderInfo::AsSkeleton),
+    };
+    let interface = definition.interface.as_ref().and_then(|interface| {
+      let found = BlockInterface::from_name(interface);
+      if found.is_none() {
+        println!("Unknown interface {} of block {}", interface, definition.name);
+      }
+      found
+    });
+    Self {
+      render: render.unwrap_or_else(|| {
+        println!("Unknown render of block {}, it won't be drawn", definition.name);
+        BlockRenderInfo::Nothing
+      }),
+      interface,
+    }
+  }
+
+  pub fn render_info(&self) -> BlockRenderInfo {
+    self.render.clone()
+  }
+
+  pub fn right_click_interface(
     &self,
-    _entity: Entity,
-    _location: DDD,
-    _commands: &mut Commands,
-    _client: &mut RenetClient,
+    entity: Entity,
+    location: DDD,
+    commands: &mut Commands,
+    client: &mut RenetClient,
   ) -> Option<()> {
-    None
+    self
+      .interface
+      .as_ref()
+      .map(|interface| interface.open(entity, location, commands, client))
   }
 }
 
-pub trait DerefExt {
-  fn deref_ext(&self) -> &'static dyn BlockTraitExt;
-}
+// Client side of every definition in the registry, indexed by id. Made again whenever the client joins a server and
+// gets its registry.
+#[derive(Resource, ExtractResource, Clone)]
+pub struct BlockExts(Arc<Vec<BlockExt>>);
 
-static BLOCK_TRAITS_EXT: [&(dyn BlockTraitExt + Sync); 8] = [
-  &regular_blocks::Air,
-  &regular_blocks::Dirt,
-  &regular_blocks::Grass,
-  &regular_blocks::Cobblestone,
-  &regular_blocks::Iron,
-  &regular_meshes::Stair,
-  &regular_meshes::Chest,
-  &regular_blocks::Furnace,
-];
+impl BlockExts {
+  pub fn new(registry: &BlockRegistry) -> Self {
+    Self(Arc::new(registry.definitions().iter().map(BlockExt::new).collect()))
+  }
 
-impl DerefExt for BlockId {
   #[inline]
-  fn deref_ext(&self) -> &'static dyn BlockTraitExt {
-    BLOCK_TRAITS_EXT[*self as usize]
+  pub fn get(&self, block: BlockId) -> &BlockExt {
+    &self.0[block.0 as usize]
   }
 }
 
-impl DerefExt for Block {
-  #[inline]
-  fn deref_ext(&self) -> &'static dyn BlockTraitExt {
-    self.block.deref_ext()
+impl FromWorld for BlockExts {
+  fn from_world(world: &mut World) -> Self {
+    Self::new(world.resource::<BlockRegistry>())
   }
 }
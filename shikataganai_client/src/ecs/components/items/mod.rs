@@ -10,6 +10,7 @@ pub enum ItemSprite {
   Coal,
   Wand,
   Iron,
+  Pickaxe,
 }
 
 impl ItemSprite {
@@ -38,8 +39,12 @@ pub trait ItemDerefExt {
   fn deref_ext(&self) -> &dyn ItemTraitExt;
 }
 
-static ITEM_TRAITS_EXT: [&(dyn ItemTraitExt + Sync); 3] =
-  [&regular_items::Coal, &regular_items::Wand, &regular_items::Iron];
+static ITEM_TRAITS_EXT: [&(dyn ItemTraitExt + Sync); 4] = [
+  &regular_items::Coal,
+  &regular_items::Wand,
+  &regular_items::Iron,
+  &regular_items::Pickaxe,
+];
 
 impl ItemDerefExt for ItemId {
   #[inline]
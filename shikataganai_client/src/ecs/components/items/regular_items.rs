@@ -3,6 +3,7 @@ use crate::ecs::components::items::{ItemSprite, ItemTraitExt};
 pub struct Coal;
 pub struct Wand;
 pub struct Iron;
+pub struct Pickaxe;
 
 impl ItemTraitExt for Coal {
   fn render_info(&self) -> ItemSprite {
@@ -21,3 +22,9 @@ impl ItemTraitExt for Iron {
     ItemSprite::Iron
   }
 }
+
+impl ItemTraitExt for Pickaxe {
+  fn render_info(&self) -> ItemSprite {
+    ItemSprite::Pickaxe
+  }
+}
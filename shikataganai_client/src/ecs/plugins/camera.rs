@@ -1,5 +1,4 @@
-use crate::ecs::components::blocks::BlockRenderInfo;
-use crate::ecs::components::blocks::DerefExt;
+use crate::ecs::components::blocks::{BlockExts, BlockRenderInfo};
 use crate::ecs::plugins::game::{in_game_input_enabled, ShikataganaiGameState};
 use crate::ecs::plugins::rendering::mesh_pipeline::loader::GltfMeshStorageHandle;
 use crate::ecs::plugins::settings::MouseSensitivity;
@@ -17,6 +16,7 @@ use bevy_rapier3d::rapier::prelude::Group as GroupRapier;
 use iyes_loopless::prelude::{ConditionSet, CurrentState, IntoConditionalSystem};
 use iyes_loopless::state::NextState;
 use num_traits::float::FloatConst;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::util::array::{to_ddd, DDD};
 
@@ -245,6 +245,7 @@ fn update_colliders(
   mesh_assets: Res<Assets<Mesh>>,
   storage: Res<GltfMeshStorageHandle>,
   mesh_storage_assets: Res<Assets<GltfMeshStorage>>,
+  (registry, block_exts): (Res<BlockRegistry>, Res<BlockExts>),
 ) {
   let player_new_position_translation = player_transform.single().translation;
   let player_new_position = to_ddd(player_new_position_translation);
@@ -256,8 +257,8 @@ fn update_colliders(
           let c = player_new_position_translation + Vec3::new(ix as f32, iy as f32, iz as f32);
           let c = to_ddd(c);
           if let Some(block) = game_world.get(c) {
-            if !block.passable() {
-              match block.deref_ext().render_info() {
+            if !registry.get(block.block).passable() {
+              match block_exts.get(block.block).render_info() {
                 BlockRenderInfo::AsBlock(_) => {
                   commands.spawn(ProximityColliderBundle::proximity_collider(
                     Collider::cuboid(0.5, 0.5, 0.5),
@@ -268,11 +269,10 @@ fn update_colliders(
                   if let Some(mesh_assets_hash_map) = mesh_storage_assets.get(&storage.0) {
                     let mesh = &mesh_assets_hash_map[&mesh];
                     let collider_mesh = mesh_assets.get(mesh.collision.as_ref().unwrap()).unwrap();
-                    let meta = block.meta.v as f32;
                     commands.spawn(ProximityColliderBundle::proximity_collider(
                       Collider::from_bevy_mesh(collider_mesh, &ComputedColliderShape::TriMesh).unwrap(), // TODO: cache this
                       Transform::from_xyz(c.0 as f32 + 0.5, c.1 as f32 + 0.5, c.2 as f32 + 0.5)
-                        .with_rotation(Quat::from_rotation_y(f32::FRAC_PI_2() * meta)),
+                        .with_rotation(block.orientation(&registry)),
                     ));
                   }
                 }
@@ -281,11 +281,10 @@ fn update_colliders(
                     let mesh = skeleton.to_skeleton_def().collider;
                     let mesh = &mesh_assets_hash_map[&mesh];
                     let collider_mesh = mesh_assets.get(mesh.collision.as_ref().unwrap()).unwrap();
-                    let meta = block.meta.v as f32;
                     commands.spawn(ProximityColliderBundle::proximity_collider(
                       Collider::from_bevy_mesh(collider_mesh, &ComputedColliderShape::TriMesh).unwrap(), // TODO: cache this
                       Transform::from_xyz(c.0 as f32 + 0.5, c.1 as f32 + 0.5, c.2 as f32 + 0.5)
-                        .with_rotation(Quat::from_rotation_y(f32::FRAC_PI_2() * meta)),
+                        .with_rotation(block.orientation(&registry)),
                     ));
                   }
                 }
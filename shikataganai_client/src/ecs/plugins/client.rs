@@ -1,37 +1,38 @@
 use bevy::prelude::*;
+use bevy::render::extract_resource::ExtractResourcePlugin;
 use bevy::utils::hashbrown::HashMap;
 use bevy_renet::renet::{ClientAuthentication, RenetClient, RenetError};
 use bevy_renet::RenetClientPlugin;
 use bincode::*;
 use flate2::read::ZlibDecoder;
-use iyes_loopless::prelude::ConditionSet;
+use iyes_loopless::prelude::{ConditionSet, NextState};
 use num_traits::{Float, FloatConst};
 use shikataganai_common::ecs::components::blocks::block_id::BlockId;
-use shikataganai_common::ecs::components::blocks::Block;
-use shikataganai_common::ecs::components::chunk::Chunk;
-use shikataganai_common::ecs::components::functors::InternalInventory;
+use shikataganai_common::ecs::components::blocks::registry::{load_definitions, BlockRegistry, BLOCK_DEFINITIONS_DIR};
+use shikataganai_common::ecs::components::chunk::{Chunk, Section};
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
 use shikataganai_common::ecs::resources::light::RelightEvent;
 use shikataganai_common::ecs::resources::player::PlayerNickname;
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::networking::{
-  client_connection_config, ClientChannel, FunctorType, NetworkFrame, PlayerCommand, PolarRotation, ServerChannel,
-  ServerMessage, PROTOCOL_ID,
+  client_connection_config, ClientChannel, FunctorType, InventoryRef, NetworkFrame, PlayerCommand, PolarRotation,
+  ServerChannel, ServerMessage, PROTOCOL_ID,
 };
 use std::io::Read;
 use std::net::UdpSocket;
+use std::path::Path;
 use std::time::SystemTime;
 use tracing::Level;
 
-use crate::ecs::components::blocks::animate;
+use crate::ecs::components::blocks::{animate, BlockExts};
 use crate::ecs::plugins::camera::{FPSCamera, Player, Recollide};
 use crate::ecs::plugins::console::ConsoleText;
-use crate::ecs::plugins::game::{in_game, LocalTick};
+use crate::ecs::plugins::game::{in_game, LocalTick, ShikataganaiGameState};
 use crate::ecs::plugins::rendering::mesh_pipeline::loader::{get_mesh_from_storage, GltfMeshStorageHandle, Meshes};
 use crate::ecs::plugins::rendering::mesh_pipeline::systems::MeshMarker;
 use crate::ecs::plugins::rendering::mesh_pipeline::AmongerTextureHandle;
 use crate::ecs::plugins::rendering::voxel_pipeline::meshing::RemeshEvent;
 use crate::ecs::resources::player::PlayerInventory;
-use crate::ecs::systems::input::add_item_inventory;
 use crate::GltfMeshStorage;
 
 #[derive(Default, Resource)]
@@ -57,9 +58,6 @@ pub struct AmongerSkeleton {
 #[derive(Component)]
 pub struct LegAnimationFrame(f32, u32);
 
-#[derive(Component)]
-pub struct Requested;
-
 pub struct ShikataganaiClientPlugin;
 
 impl Plugin for ShikataganaiClientPlugin {
@@ -72,6 +70,10 @@ impl Plugin for ShikataganaiClientPlugin {
 
     app
       .add_plugin(RenetClientPlugin { clear_events: false })
+      .add_plugin(ExtractResourcePlugin::<BlockExts>::default())
+      // Core blocks until a server sends its registry
+      .init_resource::<BlockRegistry>()
+      .init_resource::<BlockExts>()
       .init_resource::<ClientLobby>()
       .init_resource::<NetworkMapping>()
       .add_system(panic_handler)
@@ -175,7 +177,7 @@ fn receive_system(
   query_skeleton: Query<&AmongerSkeleton>,
   mut player_entity: Query<Entity, With<Player>>,
   mut fps_camera_query: Query<&mut FPSCamera>,
-  mut query: Query<&mut Transform>,
+  (mut query, mut inventory_query): (Query<&mut Transform>, Query<&mut InternalInventory>),
   mut event_writer: EventWriter<ConsoleText>,
   tick: Res<LocalTick>,
 ) {
@@ -189,6 +191,12 @@ fn receive_system(
       age: **tick,
     });
     match server_message {
+      // Comes before any chunk, ids in them follow the server's numbering
+      ServerMessage::BlockRegistry { names } => {
+        let registry = BlockRegistry::assign(load_definitions(Path::new(BLOCK_DEFINITIONS_DIR)), &names);
+        commands.insert_resource(BlockExts::new(&registry));
+        commands.insert_resource(registry);
+      }
       ServerMessage::PlayerSpawn {
         entity,
         id,
@@ -208,44 +216,68 @@ fn receive_system(
         network_mapping.0.insert(entity, client_entity);
       }
       ServerMessage::PlayerDespawn { id } => {
-        let client_entity = lobby.players.get(&id).unwrap().client_entity;
-        commands.entity(client_entity).despawn_recursive();
+        if let Some(PlayerInfo { client_entity }) = lobby.players.remove(&id) {
+          commands.entity(client_entity).despawn_recursive();
+        }
       }
       ServerMessage::BlockRemove { location } => {
         game_world.get_mut(location).map(|b| {
-          b.block = BlockId::Air;
+          b.block = BlockId::AIR;
           if b.entity != Entity::from_bits(0) {
             commands.entity(b.entity).despawn_recursive();
             b.entity = Entity::from_bits(0);
             recollide.0 = true;
           }
         });
-        remesh.send(RemeshEvent::Remesh(GameWorld::get_chunk_coord(location)));
+        remesh.send(RemeshEvent::Remesh(GameWorld::get_section_coord(location)));
       }
       ServerMessage::BlockPlace {
         location,
         block_transfer,
       } => {
         game_world.get_mut(location).map(|b| *b = block_transfer.into());
-        remesh.send(RemeshEvent::Remesh(GameWorld::get_chunk_coord(location)));
+        remesh.send(RemeshEvent::Remesh(GameWorld::get_section_coord(location)));
         recollide.0 = true;
       }
       ServerMessage::ChunkData { chunk } => {
         let mut decoder = ZlibDecoder::new(chunk.as_slice());
         let mut message = Vec::new();
         decoder.read_to_end(&mut message).unwrap();
-        let mut chunk: Chunk = deserialize(&message).unwrap();
-        chunk.grid.map_in_place(|_, block| Block {
-          entity: Entity::from_bits(0),
-          ..*block
-        });
-        let chunk_coord = GameWorld::get_chunk_coord(chunk.grid.bounds.0);
-        game_world.chunks.insert(chunk_coord, chunk);
-        game_world.remove_from_generating(chunk_coord);
-
+        let chunk: Chunk = deserialize(&message).unwrap();
+        let chunk_coord = chunk.coord;
+        // Sections that stay missing have to drop whatever mesh they had before. The loaded chunks around it meshed
+        // their border faces and corners without it, so their sections get redone as well.
         for i in chunk_coord.0 - 1..=chunk_coord.0 + 1 {
           for j in chunk_coord.1 - 1..=chunk_coord.1 + 1 {
-            remesh.send(RemeshEvent::Remesh((i, j)));
+            if (i, j) != chunk_coord && !game_world.chunks.contains_key(&(i, j)) {
+              continue;
+            }
+            for section in 0..chunk.sections.len() as i32 {
+              remesh.send(RemeshEvent::Remesh((i, section, j)));
+            }
+          }
+        }
+        game_world.insert_chunk(chunk_coord, chunk);
+      }
+      ServerMessage::SectionData {
+        chunk_coord,
+        section,
+        data,
+      } => {
+        let mut decoder = ZlibDecoder::new(data.as_slice());
+        let mut message = Vec::new();
+        decoder.read_to_end(&mut message).unwrap();
+        let mut data: Section = deserialize(&message).unwrap();
+        data.clear_entities();
+        if let Some(chunk) = game_world.chunks.get_mut(&chunk_coord) && (section as usize) < chunk.sections.len() {
+          chunk.sections[section as usize] = Some(data);
+          let section = section as i32;
+          for i in chunk_coord.0 - 1..=chunk_coord.0 + 1 {
+            for y in section - 1..=section + 1 {
+              for j in chunk_coord.1 - 1..=chunk_coord.1 + 1 {
+                remesh.send(RemeshEvent::Remesh((i, y, j)));
+              }
+            }
           }
         }
       }
@@ -272,8 +304,11 @@ fn receive_system(
               let functor: InternalInventory = deserialize(&functor).unwrap();
               commands.insert(functor);
             }
+            FunctorType::Furnace => {
+              let functor: Furnace = deserialize(&functor).unwrap();
+              commands.insert(functor);
+            }
           }
-          commands.remove::<Requested>();
           block.entity = commands.id();
         }
       }
@@ -294,8 +329,29 @@ fn receive_system(
           animate(&mut commands, entity, animation);
         }
       }
-      ServerMessage::ItemAdd { item, quant } => {
-        add_item_inventory(player_inventory.as_mut(), item, quant);
+      ServerMessage::PlayerInventory { items } => {
+        player_inventory.items = items;
+      }
+      ServerMessage::InventorySlots { inventory, slots } => {
+        let items = match inventory {
+          InventoryRef::Player => Some(&mut player_inventory.items),
+          InventoryRef::Block(location) => game_world
+            .get(location)
+            .and_then(|block| inventory_query.get_mut(block.entity).ok())
+            .map(|internal_inventory| &mut internal_inventory.into_inner().inventory),
+        };
+        if let Some(items) = items {
+          for (slot, stack) in slots {
+            if let Some(item) = items.get_mut(slot) {
+              *item = stack;
+            }
+          }
+        }
+      }
+      ServerMessage::ServerShutdown => {
+        client.disconnect();
+        commands.insert_resource(NextState(ShikataganaiGameState::MainMenu));
+        return;
       }
     }
   }
@@ -397,7 +453,7 @@ fn send_system(
   }
 }
 
-pub fn spawn_client(mut commands: Commands, _player_entity: Entity, address: String, nickname: String) {
+pub fn spawn_client(commands: &mut Commands, _player_entity: Entity, address: String, nickname: String) {
   let server_addr = address.parse().unwrap();
   let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
   let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap();
@@ -1,14 +1,17 @@
 use crate::ecs::components::blocks::{animate, AnimationInstance, AnimationTrait, ChestAnimations, Skeleton};
 use crate::ecs::plugins::camera::{Player, SelectionRes};
-use crate::ecs::resources::player::{PlayerInventory, SelectedHotBar};
+use crate::ecs::resources::player::{BlockBreaking, PlayerInventory, SelectedHotBar};
+use crate::ecs::resources::server::LocalServer;
 use crate::ecs::resources::world::ClientGameWorld;
 use crate::ecs::systems::input::{action_input, hot_bar_scroll_input, keyboard_input};
 use crate::ecs::systems::light::religh_system;
 use crate::ecs::systems::remesh::remesh_system_auxiliary;
+use crate::ecs::systems::user_interface::block_breaking::block_breaking;
 use crate::ecs::systems::user_interface::chest_inventory::{
   chest_inventory, InventoryItemMovementStatus, InventoryOpened,
 };
 use crate::ecs::systems::user_interface::connecting::connecting_window;
+use crate::ecs::systems::user_interface::furnace::{furnace_interface, FurnaceOpened};
 use crate::ecs::systems::user_interface::game_menu::game_menu;
 use crate::ecs::systems::user_interface::hot_bar::hot_bar;
 use crate::ecs::systems::user_interface::main_menu::main_menu;
@@ -22,6 +25,7 @@ use bincode::serialize;
 use iyes_loopless::prelude::*;
 use shikataganai_common::ecs::components::blocks::animation::AnimationType;
 use shikataganai_common::ecs::components::blocks::ReverseLocation;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
 use shikataganai_common::ecs::resources::player::PlayerNickname;
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::networking::{ClientChannel, PlayerCommand};
@@ -71,6 +75,7 @@ pub fn in_game_input_enabled(current_state: Res<CurrentState<ShikataganaiGameSta
 
 pub fn init_game(mut commands: Commands) {
   commands.init_resource::<SelectedHotBar>();
+  commands.init_resource::<BlockBreaking>();
   commands.init_resource::<PlayerInventory>();
   commands.init_resource::<InventoryItemMovementStatus>();
   commands.init_resource::<GameWorld>();
@@ -106,14 +111,27 @@ pub fn transition_to_simulation(
   }
 }
 
-pub fn cleanup_game(mut commands: Commands) {
+pub fn cleanup_game(mut commands: Commands, local_server: Option<ResMut<LocalServer>>) {
+  // Leaving the world stops the server it was started with, the main menu reaps it once it's done saving
+  if let Some(mut local_server) = local_server {
+    local_server.request_shutdown();
+  }
   commands.remove_resource::<SelectedHotBar>();
+  commands.remove_resource::<BlockBreaking>();
   commands.remove_resource::<PlayerInventory>();
   commands.remove_resource::<InventoryItemMovementStatus>();
   commands.remove_resource::<GameWorld>();
   commands.remove_resource::<SelectionRes>();
 }
 
+// Drops the local server once its thread is done shutting down, until then the main menu won't start another
+pub fn reap_local_server(mut commands: Commands, local_server: Option<ResMut<LocalServer>>) {
+  if let Some(mut local_server) = local_server && local_server.shutting_down && local_server.is_finished() {
+    local_server.join();
+    commands.remove_resource::<LocalServer>();
+  }
+}
+
 pub fn extract_loopless_state(mut commands: Commands, state: Extract<Res<CurrentState<ShikataganaiGameState>>>) {
   commands.insert_resource(state.clone());
 }
@@ -146,6 +164,7 @@ pub fn process_animations(
 pub fn interface_input(
   mut commands: Commands,
   inventory_opened: Option<Res<InventoryOpened>>,
+  furnace_opened: Option<Res<FurnaceOpened>>,
   player_inventory_opened: Option<Res<PlayerInventoryOpened>>,
   key: Res<Input<KeyCode>>,
   // mut physics_system: ResMut<RapierConfiguration>,
@@ -155,19 +174,40 @@ pub fn interface_input(
   if key.just_pressed(KeyCode::Escape) | key.just_pressed(KeyCode::E) {
     if let Some(inventory_opened) = inventory_opened {
       commands.remove_resource::<InventoryOpened>();
+      // Without the subscription it'd go stale, the next opening asks for it again
+      commands.entity(inventory_opened.0).remove::<InternalInventory>();
       animate(
         &mut commands,
         inventory_opened.0,
         ChestAnimations::Close.get_animation(),
       );
+      let location = reverse_location.get(inventory_opened.0).unwrap().0;
       client.send_message(
         ClientChannel::ClientCommand.id(),
         serialize(&PlayerCommand::AnimationStart {
-          location: reverse_location.get(inventory_opened.0).unwrap().0,
+          location,
           animation: ChestAnimations::Close.get_animation(),
         })
         .unwrap(),
       );
+      client.send_message(
+        ClientChannel::ClientCommand.id(),
+        serialize(&PlayerCommand::UnsubscribeFunctor { location }).unwrap(),
+      );
+    }
+    if let Some(furnace_opened) = furnace_opened {
+      commands.remove_resource::<FurnaceOpened>();
+      commands
+        .entity(furnace_opened.0)
+        .remove::<InternalInventory>()
+        .remove::<Furnace>();
+      client.send_message(
+        ClientChannel::ClientCommand.id(),
+        serialize(&PlayerCommand::UnsubscribeFunctor {
+          location: reverse_location.get(furnace_opened.0).unwrap().0,
+        })
+        .unwrap(),
+      );
     }
     if player_inventory_opened.is_some() {
       commands.remove_resource::<PlayerInventoryOpened>();
@@ -195,6 +235,7 @@ impl Plugin for GamePlugin {
     let on_main_menu = ConditionSet::new()
       .run_in_state(ShikataganaiGameState::MainMenu)
       .with_system(main_menu)
+      .with_system(reap_local_server)
       .into();
     let on_game_enter = SystemStage::parallel().with_system(init_game); //.with_system(spawn_mesh);
     let on_game_exit = SystemStage::parallel().with_system(cleanup_game);
@@ -207,6 +248,7 @@ impl Plugin for GamePlugin {
       .run_in_state(ShikataganaiGameState::Simulation)
       // .with_system(action_input)
       .with_system(hot_bar)
+      .with_system(block_breaking)
       .with_system(keyboard_input)
       // .with_system(recalculate_light_map)
       .into();
@@ -219,6 +261,7 @@ impl Plugin for GamePlugin {
       .run_in_state(ShikataganaiGameState::InterfaceOpened)
       .with_system(player_inventory)
       .with_system(chest_inventory)
+      .with_system(furnace_interface)
       .with_system(interface_input)
       .into();
     let on_game_simulation_continuous_post_update = ConditionSet::new()
@@ -1,4 +1,5 @@
 use crate::ecs::plugins::settings::{AmbientOcclusion, FullScreen, MouseSensitivity, Resolution, Settings, VSync};
+use crate::ecs::resources::server::LocalServer;
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy::winit::WinitWindows;
@@ -30,9 +31,14 @@ fn exit(
   fullscreen: Res<FullScreen>,
   ambient_occlusion: Res<AmbientOcclusion>,
   client: Option<ResMut<RenetClient>>,
+  local_server: Option<ResMut<LocalServer>>,
 ) {
   if events.iter().next().is_some() || w.windows.is_empty() {
     client.map(|mut client| client.disconnect());
+    // The process exit below would take the server thread down before its final save otherwise
+    if let Some(mut local_server) = local_server {
+      local_server.shutdown();
+    }
     let mut file = OpenOptions::new()
       .write(true)
       .create(true)
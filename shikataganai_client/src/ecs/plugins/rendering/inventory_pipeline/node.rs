@@ -1,4 +1,4 @@
-use crate::ecs::components::blocks::{BlockRenderInfo, BlockSprite, DerefExt};
+use crate::ecs::components::blocks::{BlockExts, BlockRenderInfo, BlockSprite};
 use crate::ecs::components::items::ItemDerefExt;
 use crate::ecs::plugins::rendering::inventory_pipeline::inventory_cache::{ItemRenderEntry, ItemRenderMap};
 use crate::ecs::plugins::rendering::inventory_pipeline::pipeline::InventoryNode;
@@ -111,6 +111,7 @@ impl Node for InventoryNode {
       let mut meshes_to_render = vec![];
 
       let to_render = world.resource::<ItemRenderMap>();
+      let block_exts = world.resource::<BlockExts>();
       const RADIUS: f32 = 0.49;
       let mut vertex_buffer = vec![];
       let mut rendered_item_icons = HashMap::new();
@@ -146,7 +147,7 @@ impl Node for InventoryNode {
       for (item, ItemRenderEntry { coord: (x, y), .. } ) in to_render.iter() {
         match item {
           BlockOrItem::Block(blockid) => {
-            match blockid.deref_ext().render_info() {
+            match block_exts.get(*blockid).render_info() {
               BlockRenderInfo::AsBlock(block_sprite) => {
                 add_block_to_vertices(&mut vertex_buffer, block_sprite, x, y);
               }
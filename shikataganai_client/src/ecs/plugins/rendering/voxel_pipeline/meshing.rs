@@ -3,12 +3,14 @@ use crate::ecs::plugins::rendering::voxel_pipeline::consts::{Vertex, VERTEX};
 use bevy::prelude::*;
 use bevy::render::render_resource::Buffer;
 use bytemuck_derive::{Pod, Zeroable};
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::resources::world::GameWorld;
-use shikataganai_common::util::array::{add_ddd, DD, DDD};
+use shikataganai_common::util::array::{add_ddd, DDD};
 
 #[allow(dead_code)]
 pub enum RemeshEvent {
-  Remesh(DD),
+  // Section coordinates, see GameWorld::get_section_coord
+  Remesh(DDD),
   Dummy,
 }
 
@@ -28,7 +30,16 @@ pub struct SingleVertex {
 #[derive(Copy, Clone, Pod, Zeroable)]
 pub struct SingleSide([SingleVertex; 6]);
 
-fn occluded(neighbours: &GameWorld, c: DDD, vx: f32, vy: f32, vz: f32, sx: i32, sy: i32, sz: i32) -> u8 {
+fn occluded(
+  (neighbours, registry): (&GameWorld, &BlockRegistry),
+  c: DDD,
+  vx: f32,
+  vy: f32,
+  vz: f32,
+  sx: i32,
+  sy: i32,
+  sz: i32,
+) -> u8 {
   let edgex = ((vx * 2.0) - 1.0).round() as i32;
   let edgey = ((vy * 2.0) - 1.0).round() as i32;
   let edgez = ((vz * 2.0) - 1.0).round() as i32;
@@ -43,13 +54,13 @@ fn occluded(neighbours: &GameWorld, c: DDD, vx: f32, vy: f32, vz: f32, sx: i32,
 
   let left = neighbours
     .get(add_ddd(left, c))
-    .map_or(0, |x| if x.visible() { 1 } else { 0 });
+    .map_or(0, |x| if registry.get(x.block).occludes() { 1 } else { 0 });
   let center = neighbours
     .get(add_ddd(center, c))
-    .map_or(0, |x| if x.visible() { 1 } else { 0 });
+    .map_or(0, |x| if registry.get(x.block).occludes() { 1 } else { 0 });
   let right = neighbours
     .get(add_ddd(right, c))
-    .map_or(0, |x| if x.visible() { 1 } else { 0 });
+    .map_or(0, |x| if registry.get(x.block).occludes() { 1 } else { 0 });
 
   let result = left + center + right;
   if result == 2 && center == 0 {
@@ -65,7 +76,7 @@ impl SingleSide {
     (ix, iy, iz): (i32, i32, i32),
     block: [BlockSprite; 6],
     lighting: (u8, u8),
-    neighbours: &GameWorld,
+    neighbours: (&GameWorld, &BlockRegistry),
     ambient_occlusion: bool,
   ) -> Self {
     let fx = x;
@@ -6,7 +6,7 @@ use crate::ecs::plugins::rendering::voxel_pipeline::draw_command::DrawVoxelsFull
 use crate::ecs::plugins::rendering::voxel_pipeline::meshing::RemeshEvent;
 use crate::ecs::plugins::rendering::voxel_pipeline::pipeline::VoxelPipeline;
 use crate::ecs::plugins::rendering::voxel_pipeline::systems::{extract_chunks, queue_chunks, ExtractedBlocks};
-use bevy::core_pipeline::core_3d::Opaque3d;
+use bevy::core_pipeline::core_3d::{AlphaMask3d, Opaque3d, Transparent3d};
 use bevy::prelude::*;
 use bevy::reflect::TypeUuid;
 use bevy::render::extract_resource::ExtractResourcePlugin;
@@ -28,6 +28,8 @@ pub const VOXEL_SHADER_VERTEX_HANDLE: HandleUntyped =
   HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 2763343953151597899);
 pub const VOXEL_SHADER_FRAGMENT_HANDLE: HandleUntyped =
   HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 2763343953151597999);
+pub const VOXEL_SHADER_CUTOUT_FRAGMENT_HANDLE: HandleUntyped =
+  HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 2763343953151598099);
 
 pub struct VoxelRendererPlugin;
 
@@ -57,7 +59,10 @@ impl Plugin for VoxelRendererPlugin {
     let voxel_shader_fragment =
       Shader::from_spirv(include_bytes!("../../../../../shaders/output/voxel.frag.spv").as_slice());
     shaders.set_untracked(VOXEL_SHADER_VERTEX_HANDLE, voxel_shader_vertex);
+    let voxel_shader_cutout_fragment =
+      Shader::from_spirv(include_bytes!("../../../../../shaders/output/voxel_cutout.frag.spv").as_slice());
     shaders.set_untracked(VOXEL_SHADER_FRAGMENT_HANDLE, voxel_shader_fragment);
+    shaders.set_untracked(VOXEL_SHADER_CUTOUT_FRAGMENT_HANDLE, voxel_shader_cutout_fragment);
 
     app.add_event::<RemeshEvent>();
     app.add_event::<RelightEvent>();
@@ -98,6 +103,8 @@ impl Plugin for VoxelRendererPlugin {
         },
       )
       .add_system_to_stage(RenderStage::Queue, queue_chunks.run_if(in_game))
-      .add_render_command::<Opaque3d, DrawVoxelsFull>();
+      .add_render_command::<Opaque3d, DrawVoxelsFull>()
+      .add_render_command::<AlphaMask3d, DrawVoxelsFull>()
+      .add_render_command::<Transparent3d, DrawVoxelsFull>();
   }
 }
@@ -1,4 +1,6 @@
-use crate::ecs::plugins::rendering::voxel_pipeline::{VOXEL_SHADER_FRAGMENT_HANDLE, VOXEL_SHADER_VERTEX_HANDLE};
+use crate::ecs::plugins::rendering::voxel_pipeline::{
+  VOXEL_SHADER_CUTOUT_FRAGMENT_HANDLE, VOXEL_SHADER_FRAGMENT_HANDLE, VOXEL_SHADER_VERTEX_HANDLE,
+};
 use bevy::prelude::*;
 use bevy::render::mesh::PrimitiveTopology;
 use bevy::render::render_resource::ShaderType;
@@ -11,6 +13,7 @@ use bevy::render::render_resource::{
 use bevy::render::renderer::RenderDevice;
 use bevy::render::texture::BevyDefault;
 use bevy::render::view::ViewUniform;
+use shikataganai_common::ecs::components::blocks::registry::RenderLayer;
 use wgpu::BindGroupLayoutDescriptor;
 
 #[derive(Resource)]
@@ -22,9 +25,9 @@ pub struct VoxelPipeline {
 }
 
 impl SpecializedRenderPipeline for VoxelPipeline {
-  type Key = ();
+  type Key = RenderLayer;
 
-  fn specialize(&self, _key: Self::Key) -> RenderPipelineDescriptor {
+  fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
     let shader_defs = Vec::new();
     let vertex_formats = vec![
       VertexFormat::Float32x3,
@@ -43,7 +46,10 @@ impl SpecializedRenderPipeline for VoxelPipeline {
         buffers: vec![vertex_layout],
       },
       fragment: Some(FragmentState {
-        shader: VOXEL_SHADER_FRAGMENT_HANDLE.typed::<Shader>(),
+        shader: match key {
+          RenderLayer::Cutout => VOXEL_SHADER_CUTOUT_FRAGMENT_HANDLE.typed::<Shader>(),
+          _ => VOXEL_SHADER_FRAGMENT_HANDLE.typed::<Shader>(),
+        },
         shader_defs,
         entry_point: "main".into(),
         targets: vec![Some(ColorTargetState {
@@ -69,7 +75,8 @@ impl SpecializedRenderPipeline for VoxelPipeline {
       },
       depth_stencil: Some(DepthStencilState {
         format: TextureFormat::Depth32Float,
-        depth_write_enabled: true,
+        // Translucent faces mustn't hide the ones behind them that get drawn later
+        depth_write_enabled: key != RenderLayer::Translucent,
         depth_compare: CompareFunction::GreaterEqual,
         stencil: Default::default(),
         bias: Default::default(),
@@ -1,4 +1,4 @@
-use crate::ecs::components::blocks::{BlockRenderInfo, DerefExt};
+use crate::ecs::components::blocks::{oriented_sprites, BlockExts, BlockRenderInfo};
 use crate::ecs::plugins::camera::{Selection, SelectionRes};
 use crate::ecs::plugins::rendering::voxel_pipeline::bind_groups::{
   LightTextureBindGroup, LightTextureHandle, SelectionBindGroup, TextureHandle, VoxelTextureBindGroup,
@@ -8,26 +8,29 @@ use crate::ecs::plugins::rendering::voxel_pipeline::draw_command::DrawVoxelsFull
 use crate::ecs::plugins::rendering::voxel_pipeline::meshing::{ChunkMeshBuffer, RemeshEvent, SingleSide};
 use crate::ecs::plugins::rendering::voxel_pipeline::pipeline::VoxelPipeline;
 use crate::ecs::plugins::settings::AmbientOcclusion;
-use bevy::core_pipeline::core_3d::Opaque3d;
+use bevy::core_pipeline::core_3d::{AlphaMask3d, Opaque3d, Transparent3d};
 use bevy::prelude::*;
 use bevy::render::render_asset::RenderAssets;
 use bevy::render::render_phase::{DrawFunctions, RenderPhase};
 use bevy::render::render_resource::{BufferUsages, BufferVec, PipelineCache, SpecializedRenderPipelines};
 use bevy::render::renderer::{RenderDevice, RenderQueue};
-use bevy::render::view::ViewUniforms;
+use bevy::render::view::{ExtractedView, ViewUniforms};
 use bevy::render::Extract;
 use bevy::utils::hashbrown::HashMap;
 use itertools::Itertools;
+use shikataganai_common::ecs::components::blocks::registry::{BlockRegistry, RenderLayer};
 use shikataganai_common::ecs::components::blocks::Block;
+use shikataganai_common::ecs::components::chunk::{section_bounds, SECTION_HEIGHT};
 use shikataganai_common::ecs::resources::world::GameWorld;
-use shikataganai_common::util::array::{sub_ddd, ArrayIndex, ImmediateNeighbours, DD};
+use shikataganai_common::util::array::{sub_ddd, ArrayIndex, ImmediateNeighbours, DDD};
 use std::ops::Deref;
 use wgpu::util::BufferInitDescriptor;
 use wgpu::{BindGroupDescriptor, BindGroupEntry, BindingResource};
 
 #[derive(Resource)]
 pub struct ExtractedBlocks {
-  pub blocks: HashMap<DD, BufferVec<SingleSide>>,
+  // Faces of a section, one buffer for every render layer it has blocks of
+  pub blocks: HashMap<(DDD, RenderLayer), BufferVec<SingleSide>>,
 }
 
 impl Default for ExtractedBlocks {
@@ -42,6 +45,7 @@ pub fn extract_chunks(
   selection: Extract<Res<SelectionRes>>,
   mut remesh_events: Extract<EventReader<RemeshEvent>>,
   ambient_occlusion: Extract<Res<AmbientOcclusion>>,
+  (registry, block_exts): (Extract<Res<BlockRegistry>>, Extract<Res<BlockExts>>),
   mut extracted_blocks: ResMut<ExtractedBlocks>,
 ) {
   commands.insert_resource(selection.clone());
@@ -52,39 +56,49 @@ pub fn extract_chunks(
     .filter_map(|p| if let RemeshEvent::Remesh(d) = p { Some(d) } else { None })
     .unique()
   {
-    if !game_world.chunks.contains_key(ch) {
+    let chunk = match game_world.chunks.get(&(ch.0, ch.2)) {
+      Some(chunk) if ch.1 >= 0 && ch.1 < chunk.sections.len() as i32 => chunk,
+      _ => continue,
+    };
+    // Nothing to draw in a missing section
+    if chunk.sections[ch.1 as usize].is_none() {
+      for layer in RenderLayer::ALL {
+        extracted_blocks.blocks.remove(&(*ch, layer));
+      }
       continue;
     }
-    updated.push(*ch);
-    extracted_blocks
-      .blocks
-      .insert(*ch, BufferVec::new(BufferUsages::VERTEX));
-    let extracted_blocks = extracted_blocks.blocks.get_mut(ch).unwrap();
-    let bounds = game_world.chunks[ch].grid.bounds;
+    let mut layers: HashMap<RenderLayer, BufferVec<SingleSide>> = HashMap::new();
+    let bounds = section_bounds(chunk.coord, ch.1 as usize);
     let mut i = bounds.0;
     loop {
       let block: Block = *game_world.get(i).unwrap();
-      match block.deref_ext().render_info() {
+      let layer = registry.get(block.block).layer;
+      match block_exts.get(block.block).render_info() {
         BlockRenderInfo::Nothing => {}
         BlockRenderInfo::AsBlock(block_sprites) => {
-          if block.visible() {
-            for neighbour in i.immediate_neighbours() {
-              if game_world.get(neighbour).map_or(true, |b| !b.visible()) {
-                let light_level = game_world.get_light_level(neighbour);
-                let lighting = match light_level {
-                  Some(light_level) => (light_level.heaven, light_level.hearth),
-                  None => (0, 0),
-                };
+          for neighbour in i.immediate_neighbours() {
+            // Faces between two blocks of the same translucent kind aren't drawn either, glass walls stay clear
+            let hidden = game_world.get(neighbour).is_some_and(|b| {
+              registry.get(b.block).occludes() || (layer != RenderLayer::Opaque && b.block == block.block)
+            });
+            if !hidden {
+              let light_level = game_world.get_light_level(neighbour);
+              let lighting = match light_level {
+                Some(light_level) => (light_level.heaven, light_level.hearth),
+                None => (0, 0),
+              };
 
-                extracted_blocks.push(SingleSide::new(
+              layers
+                .entry(layer)
+                .or_insert_with(|| BufferVec::new(BufferUsages::VERTEX))
+                .push(SingleSide::new(
                   (i.0 as f32, i.1 as f32, i.2 as f32),
                   sub_ddd(neighbour, i),
-                  block_sprites,
+                  oriented_sprites(block_sprites, block.orientation(&registry)),
                   lighting,
-                  &game_world,
+                  (&game_world, &registry),
                   ambient_occlusion.0,
                 ));
-              }
             }
           }
         }
@@ -96,18 +110,38 @@ pub fn extract_chunks(
         Some(i) => i,
       }
     }
+    for layer in RenderLayer::ALL {
+      match layers.remove(&layer) {
+        Some(buf) => {
+          updated.push((*ch, layer));
+          extracted_blocks.blocks.insert((*ch, layer), buf);
+        }
+        None => {
+          extracted_blocks.blocks.remove(&(*ch, layer));
+        }
+      }
+    }
   }
   commands.insert_resource(updated);
 }
 
 #[derive(Default, Deref, DerefMut, Resource)]
-pub struct UpdatedVec(pub Vec<DD>);
+pub struct UpdatedVec(pub Vec<(DDD, RenderLayer)>);
 
 pub fn queue_chunks(
   mut commands: Commands,
   mut extracted_blocks: ResMut<ExtractedBlocks>,
-  mut views: Query<&mut RenderPhase<Opaque3d>>,
-  draw_functions: Res<DrawFunctions<Opaque3d>>,
+  mut views: Query<(
+    &ExtractedView,
+    &mut RenderPhase<Opaque3d>,
+    &mut RenderPhase<AlphaMask3d>,
+    &mut RenderPhase<Transparent3d>,
+  )>,
+  draw_functions: (
+    Res<DrawFunctions<Opaque3d>>,
+    Res<DrawFunctions<AlphaMask3d>>,
+    Res<DrawFunctions<Transparent3d>>,
+  ),
   mut pipelines: ResMut<SpecializedRenderPipelines<VoxelPipeline>>,
   mut pipeline_cache: ResMut<PipelineCache>,
   chunk_pipeline: Res<VoxelPipeline>,
@@ -190,27 +224,48 @@ pub fn queue_chunks(
     }),
   });
 
-  let draw_function = draw_functions.read().get_id::<DrawVoxelsFull>().unwrap();
-
-  let pipeline = pipelines.specialize(&mut pipeline_cache, &chunk_pipeline, ());
+  let draw_function_opaque = draw_functions.0.read().get_id::<DrawVoxelsFull>().unwrap();
+  let draw_function_cutout = draw_functions.1.read().get_id::<DrawVoxelsFull>().unwrap();
+  let draw_function_translucent = draw_functions.2.read().get_id::<DrawVoxelsFull>().unwrap();
 
   let buf = &mut extracted_blocks.blocks;
   for i in updated.iter() {
     let buf = buf.get_mut(i).unwrap();
     buf.write_buffer(&render_device, &render_queue);
   }
-  for (_, buf) in buf.iter_mut() {
+  for ((section, layer), buf) in buf.iter_mut() {
     if !buf.is_empty() {
+      let pipeline = pipelines.specialize(&mut pipeline_cache, &chunk_pipeline, *layer);
       let entity = commands
         .spawn(ChunkMeshBuffer(buf.buffer().unwrap().clone(), buf.len()))
         .id();
-      for mut view in views.iter_mut() {
-        view.add(Opaque3d {
-          distance: 2.0,
-          draw_function,
-          pipeline,
-          entity,
-        });
+      // Translucent faces are sorted by section only, the faces within a section are drawn in any order
+      let center = Mat4::from_translation(Vec3::new(
+        section.0 as f32 * 16.0 + 8.0,
+        (section.1 * SECTION_HEIGHT) as f32 + SECTION_HEIGHT as f32 / 2.0,
+        section.2 as f32 * 16.0 + 8.0,
+      ));
+      for (view, mut opaque, mut cutout, mut translucent) in views.iter_mut() {
+        match layer {
+          RenderLayer::Opaque => opaque.add(Opaque3d {
+            distance: 2.0,
+            draw_function: draw_function_opaque,
+            pipeline,
+            entity,
+          }),
+          RenderLayer::Cutout => cutout.add(AlphaMask3d {
+            distance: 2.0,
+            draw_function: draw_function_cutout,
+            pipeline,
+            entity,
+          }),
+          RenderLayer::Translucent => translucent.add(Transparent3d {
+            distance: view.rangefinder3d().distance(&center),
+            draw_function: draw_function_translucent,
+            pipeline,
+            entity,
+          }),
+        }
       }
     }
   }
@@ -1,3 +1,4 @@
 pub mod light;
 pub mod player;
+pub mod server;
 pub mod world;
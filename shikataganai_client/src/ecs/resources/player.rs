@@ -1,62 +1,31 @@
 use bevy::prelude::Resource;
-use shikataganai_common::ecs::components::blocks::block_id::BlockId;
-use shikataganai_common::ecs::components::blocks::{BlockOrItem, QuantifiedBlockOrItem};
-use shikataganai_common::ecs::components::item::ItemId;
+use shikataganai_common::ecs::components::blocks::QuantifiedBlockOrItem;
+use shikataganai_common::ecs::resources::player::{HOT_BAR_WIDTH, PLAYER_INVENTORY_SIZE};
+use shikataganai_common::util::array::DDD;
 
 #[derive(Resource, Default)]
 pub struct SelectedHotBar(pub i32);
 
+// Block being mined, the server gets told about it when it starts and once the break time is up
+#[derive(Resource, Default)]
+pub struct BlockBreaking {
+  pub location: Option<DDD>,
+  pub elapsed: f32,
+  pub duration: f32,
+}
+
 #[derive(Resource)]
 pub struct PlayerInventory {
   pub hot_bar_width: usize,
   pub items: Vec<Option<QuantifiedBlockOrItem>>,
 }
 
+// Filled in by the server once the player is in, it owns what's in there
 impl Default for PlayerInventory {
   fn default() -> Self {
     Self {
-      hot_bar_width: 9,
-      items: vec![
-        Some(QuantifiedBlockOrItem {
-          block_or_item: BlockOrItem::Block(BlockId::Stair),
-          quant: 100,
-        }),
-        Some(QuantifiedBlockOrItem {
-          block_or_item: BlockOrItem::Block(BlockId::Chest),
-          quant: 100,
-        }),
-        None,
-        Some(QuantifiedBlockOrItem {
-          block_or_item: BlockOrItem::Item(ItemId::Coal),
-          quant: 2,
-        }),
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        None,
-        Some(QuantifiedBlockOrItem {
-          block_or_item: BlockOrItem::Block(BlockId::Grass),
-          quant: 25,
-        }),
-        None,
-        None,
-        None,
-        None,
-        None,
-      ],
+      hot_bar_width: HOT_BAR_WIDTH,
+      items: vec![None; PLAYER_INVENTORY_SIZE],
     }
   }
 }
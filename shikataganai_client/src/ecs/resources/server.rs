@@ -0,0 +1,35 @@
+use bevy::prelude::*;
+use shikataganai_server::ecs::resources::control::ServerControl;
+use std::thread::JoinHandle;
+
+// Server started from the main menu, it runs on its own thread for as long as the client stays in its world
+#[derive(Resource)]
+pub struct LocalServer {
+  pub control: ServerControl,
+  pub thread: Option<JoinHandle<()>>,
+  pub shutting_down: bool,
+}
+
+impl LocalServer {
+  // Lets it do the final save and tell the clients it's going down without waiting for it
+  pub fn request_shutdown(&mut self) {
+    self.control.request_shutdown();
+    self.shutting_down = true;
+  }
+
+  // Same, but waits for the thread to finish. Only for when the whole client is going away.
+  pub fn shutdown(&mut self) {
+    self.request_shutdown();
+    self.join();
+  }
+
+  pub fn is_finished(&self) -> bool {
+    self.thread.as_ref().map_or(true, |thread| thread.is_finished())
+  }
+
+  pub fn join(&mut self) {
+    if let Some(thread) = self.thread.take() && thread.join().is_err() {
+      warn!("The local server panicked while shutting down");
+    }
+  }
+}
@@ -2,7 +2,7 @@ use crate::ecs::plugins::client::send_message;
 use bevy_renet::renet::RenetClient;
 use shikataganai_common::ecs::components::blocks::Block;
 use shikataganai_common::ecs::components::chunk::Chunk;
-use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::ecs::resources::world::{ChunkState, GameWorld};
 use shikataganai_common::networking::PlayerCommand;
 use shikataganai_common::util::array::{DD, DDD};
 
@@ -17,8 +17,8 @@ impl ClientGameWorld for GameWorld {
   fn get_chunk_or_request(&mut self, chunk_coord: DD, client: &mut RenetClient) -> Option<&Chunk> {
     match self.chunks.get(&chunk_coord) {
       None => {
-        if !self.generating.contains(&chunk_coord) {
-          self.generating.push(chunk_coord);
+        if self.chunk_state(chunk_coord).is_none() {
+          self.states.insert(chunk_coord, ChunkState::Requested);
           send_message(client, PlayerCommand::RequestChunk { chunk_coord });
         }
         None
@@ -30,8 +30,8 @@ impl ClientGameWorld for GameWorld {
   fn get_chunk_or_request_mut(&mut self, chunk_coord: DD, client: &mut RenetClient) -> Option<&mut Chunk> {
     match self.chunks.get_mut(&chunk_coord) {
       None => {
-        if !self.generating.contains(&chunk_coord) {
-          self.generating.push(chunk_coord);
+        if self.chunk_state(chunk_coord).is_none() {
+          self.states.insert(chunk_coord, ChunkState::Requested);
           send_message(client, PlayerCommand::RequestChunk { chunk_coord });
         }
         None
@@ -44,14 +44,14 @@ impl ClientGameWorld for GameWorld {
     let chunk_coord = GameWorld::get_chunk_coord(coord);
     self
       .get_chunk_or_request(chunk_coord, client)
-      .map(|chunk| &chunk.grid[coord])
+      .and_then(|chunk| chunk.get(coord))
   }
 
   fn get_block_or_request_mut(&mut self, coord: DDD, client: &mut RenetClient) -> Option<&mut Block> {
     let chunk_coord = GameWorld::get_chunk_coord(coord);
     self
       .get_chunk_or_request_mut(chunk_coord, client)
-      .map(|chunk| &mut chunk.grid[coord])
+      .and_then(|chunk| chunk.get_mut(coord))
   }
 }
 
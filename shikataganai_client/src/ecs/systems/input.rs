@@ -1,7 +1,7 @@
-use crate::ecs::components::blocks::DerefExt;
+use crate::ecs::components::blocks::BlockExts;
 use crate::ecs::plugins::camera::{FPSCamera, Recollide, Selection, SelectionRes};
 use crate::ecs::plugins::game::ShikataganaiGameState;
-use crate::ecs::resources::player::{PlayerInventory, SelectedHotBar};
+use crate::ecs::resources::player::{BlockBreaking, PlayerInventory, SelectedHotBar};
 use crate::ecs::systems::user_interface::player_inventory::PlayerInventoryOpened;
 use bevy::input::keyboard::KeyboardInput;
 use bevy::input::mouse::MouseWheel;
@@ -12,24 +12,24 @@ use bevy_rapier3d::prelude::{Collider, InteractionGroups, RapierContext};
 use bevy_rapier3d::rapier::prelude::Group;
 use bevy_renet::renet::RenetClient;
 use bincode::serialize;
-use itertools::Itertools;
 use iyes_loopless::prelude::NextState;
 use num_traits::FloatConst;
 use shikataganai_common::ecs::components::blocks::block_id::BlockId;
-use shikataganai_common::ecs::components::blocks::{Block, BlockOrItem, BlockRotation, QuantifiedBlockOrItem};
-use shikataganai_common::ecs::components::item::ItemId;
-use shikataganai_common::ecs::resources::light::{LightLevel, RelightEvent};
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::state::{Facing, Half};
+use shikataganai_common::ecs::components::blocks::{Block, BlockMeta, BlockOrItem, QuantifiedBlockOrItem};
+use shikataganai_common::ecs::resources::light::{seed_light, RelightEvent};
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::networking::{ClientChannel, PlayerCommand};
 use shikataganai_common::util::array::DDD;
-use std::cmp::Ordering;
 use std::ops::Deref;
 
 fn place_item_from_inventory(
   player_inventory: &mut PlayerInventory,
   item_idx: usize,
   coord: DDD,
-  game_world: &mut GameWorld,
+  half: Half,
+  (game_world, registry): (&mut GameWorld, &BlockRegistry),
   rapier_context: &RapierContext,
   camera: &FPSCamera,
 ) -> Option<Block> {
@@ -57,20 +57,24 @@ fn place_item_from_inventory(
         )
         .is_none()
       {
+        target_negative_block.block = *block;
+        target_negative_block.meta = BlockMeta { v: 0 };
         let mut phi = (camera.phi - f32::FRAC_PI_4()) % (f32::PI() * 2.0);
         if phi < 0.0 {
           phi += f32::PI() * 2.0;
         }
-        if phi > 0.0 && phi <= f32::FRAC_PI_2() {
-          target_negative_block.meta.set_rotation(BlockRotation::WEST);
+        let facing = if phi > 0.0 && phi <= f32::FRAC_PI_2() {
+          Facing::West
         } else if phi > f32::FRAC_PI_2() && phi <= f32::PI() {
-          target_negative_block.meta.set_rotation(BlockRotation::SOUTH);
+          Facing::South
         } else if phi > f32::PI() && phi <= f32::PI() + f32::FRAC_PI_2() {
-          target_negative_block.meta.set_rotation(BlockRotation::EAST);
+          Facing::East
         } else {
-          target_negative_block.meta.set_rotation(BlockRotation::NORTH);
-        }
-        target_negative_block.block = *block;
+          Facing::North
+        };
+        // Blocks without these properties just ignore them
+        target_negative_block.set(registry, facing);
+        target_negative_block.set(registry, half);
         *quant -= 1;
         if *quant <= 0 {
           player_inventory.items[item_idx] = None;
@@ -87,111 +91,33 @@ fn place_item_from_inventory(
   }
 }
 
-pub fn add_item_inventory(player_inventory: &mut PlayerInventory, item_id: ItemId, quant: u32) -> Option<()> {
-  match player_inventory
-    .items
-    .iter_mut()
-    .filter(|slot| {
-      slot
-        .as_ref()
-        .map(|item| item.block_or_item == BlockOrItem::Item(item_id))
-        .unwrap_or(true)
-    })
-    .sorted_by(|slot1, slot2| {
-      if let Some(slot1) = slot1 && let Some(_slot2) = slot2 {
-        if slot1.block_or_item == BlockOrItem::Item(item_id) {
-          Ordering::Greater
-        } else {
-          Ordering::Less
-        }
-      } else if slot1.is_some() {
-        Ordering::Less
-      } else {
-        Ordering::Greater
-      }
-    })
-    .next()
-    .map(|slot| {
-      slot.get_or_insert(QuantifiedBlockOrItem {
-        block_or_item: BlockOrItem::Item(item_id),
-        quant: 0,
-      })
-    })
-    .map(|slot| {
-      slot.quant += quant;
-      slot.quant == 1
-    }) {
-    None => {
-      return None;
-    }
-    Some(true) => {}
-    _ => {}
-  }
-
-  Some(())
-}
-
-fn pick_up_block(
+// Drops come from the server once it agrees the block is broken
+fn break_block(
   commands: &mut Commands,
-  player_inventory: &mut PlayerInventory,
   coord: DDD,
   game_world: &mut GameWorld,
+  registry: &BlockRegistry,
 ) -> Option<()> {
-  if let Some(source_block) = game_world.get_mut(coord) {
-    let block: BlockId = std::mem::replace(&mut source_block.block, BlockId::Air);
-
-    // Check for the first slot in inventory containing required block or the next empty slot to insert that block into.
-    match player_inventory
-      .items
-      .iter_mut()
-      .filter(|slot| {
-        slot
-          .as_ref()
-          .map(|item| item.block_or_item == BlockOrItem::Block(block))
-          .unwrap_or(true)
-      })
-      .sorted_by(|slot1, slot2| {
-        if let Some(slot1) = slot1 && let Some(_slot2) = slot2 {
-          if slot1.block_or_item == BlockOrItem::Block(block) {
-            Ordering::Greater
-          } else {
-            Ordering::Less
-          }
-        } else if slot1.is_some() {
-          Ordering::Less
-        } else {
-          Ordering::Greater
-        }
-      })
-      .next()
-      .map(|slot| {
-        slot.get_or_insert(QuantifiedBlockOrItem {
-          block_or_item: BlockOrItem::Block(block),
-          quant: 0,
-        })
-      })
-      .map(|slot| {
-        slot.quant += 1;
-        slot.quant == 1
-      }) {
-      None => {
-        // No empty or correct slots found, return block back into place
-        let _ = std::mem::replace(&mut source_block.block, block);
-        return None;
-      }
-      Some(true) => {
-        // Pick up, do the rerender event
-      }
-      _ => {}
-    }
+  let source_block = game_world.get_mut(coord)?;
+  if source_block.block == BlockId::AIR {
+    return None;
+  }
+  source_block.block = BlockId::AIR;
+  if source_block.entity != Entity::from_bits(0) {
+    commands.entity(source_block.entity).despawn_recursive();
+    source_block.entity = Entity::from_bits(0);
+  }
+  seed_light(game_world, registry, coord);
+  Some(())
+}
 
-    if source_block.entity != Entity::from_bits(0) {
-      commands.entity(source_block.entity).despawn_recursive();
-      source_block.entity = Entity::from_bits(0);
-    }
-    Some(())
-  } else {
-    None
+fn abort_block_breaking(block_breaking: &mut BlockBreaking, client: &mut RenetClient) {
+  if block_breaking.location.is_some() {
+    *block_breaking = BlockBreaking::default();
+    client.send_message(
+      ClientChannel::ClientCommand.id(),
+      serialize(&PlayerCommand::BlockBreakAbort).unwrap(),
+    );
   }
 }
 
@@ -230,38 +156,77 @@ pub fn action_input(
   rapier_context: Res<RapierContext>,
   mut recollide: ResMut<Recollide>,
   mut client: ResMut<RenetClient>,
+  mut block_breaking: ResMut<BlockBreaking>,
+  time: Res<Time>,
+  (registry, block_exts): (Res<BlockRegistry>, Res<BlockExts>),
 ) {
   match selection.into_inner().deref() {
-    None => {}
+    None => abort_block_breaking(block_breaking.as_mut(), client.as_mut()),
     Some(Selection { cube, face }) => {
       let source: DDD = *cube;
       let target_negative = *face;
-      if mouse.just_pressed(MouseButton::Left) {
-        if let Some(()) = pick_up_block(&mut commands, player_inventory.as_mut(), source, &mut game_world) {
+      if !mouse.pressed(MouseButton::Left) {
+        abort_block_breaking(block_breaking.as_mut(), client.as_mut());
+      } else if block_breaking.location != Some(source) {
+        let tool = match player_inventory.items.get(hotbar_selection.0 as usize) {
+          Some(Some(QuantifiedBlockOrItem {
+            block_or_item: BlockOrItem::Item(item),
+            ..
+          })) => Some(*item),
+          _ => None,
+        };
+        if let Some(block) = game_world.get(source) {
+          *block_breaking = BlockBreaking {
+            location: Some(source),
+            elapsed: 0.0,
+            duration: registry.get(block.block).break_time(tool),
+          };
           client.send_message(
             ClientChannel::ClientCommand.id(),
-            serialize(&PlayerCommand::BlockRemove { location: source }).unwrap(),
+            serialize(&PlayerCommand::BlockBreakStart {
+              location: source,
+              slot: hotbar_selection.0 as usize,
+            })
+            .unwrap(),
           );
+        }
+      } else {
+        block_breaking.elapsed += time.delta_seconds();
+        if block_breaking.elapsed >= block_breaking.duration {
+          *block_breaking = BlockBreaking::default();
+          if let Some(()) = break_block(&mut commands, source, &mut game_world, &registry) {
+            client.send_message(
+              ClientChannel::ClientCommand.id(),
+              serialize(&PlayerCommand::BlockRemove { location: source }).unwrap(),
+            );
 
-          relight_events.send(RelightEvent::Relight(source));
-          recollide.0 = true;
+            relight_events.send(RelightEvent::Relight(source));
+            recollide.0 = true;
+          }
         }
       }
       if mouse.just_pressed(MouseButton::Right)
         && game_world
           .get(source)
           .and_then(|block| {
-            block
-              .deref_ext()
+            block_exts
+              .get(block.block)
               .right_click_interface(block.entity, source, &mut commands, &mut client)
           })
           .is_none()
       {
+        // Placed against the underside of a block it hangs from the top half
+        let half = if target_negative.1 < source.1 {
+          Half::Top
+        } else {
+          Half::Bottom
+        };
         let block_copy = place_item_from_inventory(
           player_inventory.as_mut(),
           hotbar_selection.0 as usize,
           target_negative,
-          &mut game_world,
+          half,
+          (&mut game_world, &registry),
           &rapier_context,
           camera.single(),
         );
@@ -272,6 +237,7 @@ pub fn action_input(
             serialize(&PlayerCommand::BlockPlace {
               location: target_negative,
               block_transfer: block.into(),
+              slot: hotbar_selection.0 as usize,
             })
             .unwrap(),
           );
@@ -283,7 +249,7 @@ pub fn action_input(
           );
         }
 
-        game_world.set_light_level(target_negative, LightLevel::dark());
+        seed_light(&mut game_world, &registry, target_negative);
 
         relight_events.send(RelightEvent::Relight(target_negative));
         recollide.0 = true;
@@ -1,21 +1,23 @@
 use crate::ecs::plugins::rendering::voxel_pipeline::meshing::RemeshEvent;
 use bevy::prelude::*;
 use itertools::Itertools;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::resources::light::{relight_helper, RelightEvent};
 use shikataganai_common::ecs::resources::world::GameWorld;
-use shikataganai_common::util::array::FlatFullNeighbours;
+use shikataganai_common::util::array::FullNeighbours;
 
 pub fn religh_system(
   mut relight: EventReader<RelightEvent>,
   mut remesh: EventWriter<RemeshEvent>,
   mut game_world: ResMut<GameWorld>,
+  registry: Res<BlockRegistry>,
 ) {
-  for coord in relight_helper(&mut relight, game_world.as_mut()).iter() {
+  for coord in relight_helper(&mut relight, game_world.as_mut(), registry.as_ref()).iter() {
     coord
-      .flat_full_neighbours()
-      .map(GameWorld::get_chunk_coord)
+      .full_neighbours()
+      .map(GameWorld::get_section_coord)
       .unique()
-      .for_each(|chunk_coord| remesh.send(RemeshEvent::Remesh(chunk_coord)));
+      .for_each(|section_coord| remesh.send(RemeshEvent::Remesh(section_coord)));
   }
 }
 //
@@ -1,5 +1,4 @@
-use crate::ecs::components::blocks::DerefExt;
-use crate::ecs::components::blocks::{BlockRenderInfo, Skeleton};
+use crate::ecs::components::blocks::{BlockExts, BlockRenderInfo, Skeleton};
 use crate::ecs::plugins::rendering::mesh_pipeline::loader::GltfMeshStorageHandle;
 use crate::ecs::plugins::rendering::mesh_pipeline::systems::MeshMarker;
 use crate::ecs::plugins::rendering::voxel_pipeline::meshing::RemeshEvent;
@@ -7,8 +6,9 @@ use crate::GltfMeshStorage;
 use bevy::prelude::*;
 use bevy::utils::hashbrown::HashMap;
 use itertools::Itertools;
-use num_traits::FloatConst;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::components::blocks::ReverseLocation;
+use shikataganai_common::ecs::components::chunk::section_bounds;
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::util::array::{from_ddd, ArrayIndex};
 
@@ -21,20 +21,24 @@ pub fn remesh_system_auxiliary(
   mut remesh_events: EventReader<RemeshEvent>,
   storage: Res<GltfMeshStorageHandle>,
   mesh_storage_assets: Res<Assets<GltfMeshStorage>>,
+  (registry, block_exts): (Res<BlockRegistry>, Res<BlockExts>),
 ) {
   for ch in remesh_events
     .iter()
     .filter_map(|p| if let RemeshEvent::Remesh(d) = p { Some(d) } else { None })
     .unique()
   {
-    if !game_world.chunks.contains_key(ch) {
-      continue;
-    }
-    let bounds = game_world.chunks.get(ch).unwrap().grid.bounds;
+    let bounds = match game_world.chunks.get(&(ch.0, ch.2)) {
+      // Missing sections are air, no meshes to place
+      Some(chunk) if ch.1 >= 0 && ch.1 < chunk.sections.len() as i32 && chunk.sections[ch.1 as usize].is_some() => {
+        section_bounds(chunk.coord, ch.1 as usize)
+      }
+      _ => continue,
+    };
     let mut i = bounds.0;
     loop {
       let mut block = game_world.get_mut(i).unwrap();
-      if block.need_reverse_location() {
+      if registry.get(block.block).need_reverse_location() {
         block.entity = if block.entity == Entity::from_bits(0) {
           commands.spawn_empty()
         } else {
@@ -43,26 +47,27 @@ pub fn remesh_system_auxiliary(
         .insert(ReverseLocation(i))
         .id();
       }
-      match block.deref_ext().render_info() {
+      match block_exts.get(block.block).render_info() {
         BlockRenderInfo::AsMesh(mesh) => {
           if block.entity == Entity::from_bits(0) {
             if let Some(mesh_assets_hash_map) = mesh_storage_assets.get(&storage.0) {
               let mesh = &mesh_assets_hash_map[&mesh];
               let render_mesh: &Handle<Mesh> = mesh.render.as_ref().unwrap();
-              let rotation = block.meta.get_rotation();
               let e = commands
                 .spawn((
                   render_mesh.clone(),
                   MeshMarker,
                   Transform::from_translation(from_ddd(i) + Vec3::new(0.5, 0.5, 0.5))
-                    .with_rotation(Quat::from_rotation_y(f32::PI() / 2.0 * rotation as i32 as f32)),
+                    .with_rotation(block.orientation(&registry)),
                   GlobalTransform::default(),
                 ))
                 .id();
               block.entity = e;
             }
           } else if mesh_query.get(block.entity).is_ok() {
-            transform_query.get_mut(block.entity).unwrap().translation = from_ddd(i) + Vec3::new(0.5, 0.5, 0.5);
+            let mut transform = transform_query.get_mut(block.entity).unwrap();
+            transform.translation = from_ddd(i) + Vec3::new(0.5, 0.5, 0.5);
+            transform.rotation = block.orientation(&registry);
           } else {
             if let Some(mesh_assets_hash_map) = mesh_storage_assets.get(&storage.0) {
               let mesh = &mesh_assets_hash_map[&mesh];
@@ -71,17 +76,22 @@ pub fn remesh_system_auxiliary(
                 .entity(block.entity)
                 .insert(MeshMarker)
                 .insert(render_mesh.clone())
-                .insert(Transform::from_translation(from_ddd(i) + Vec3::new(0.5, 0.5, 0.5)))
+                .insert(
+                  Transform::from_translation(from_ddd(i) + Vec3::new(0.5, 0.5, 0.5))
+                    .with_rotation(block.orientation(&registry)),
+                )
                 .insert(GlobalTransform::default());
             }
           }
         }
         BlockRenderInfo::AsSkeleton(skeleton) => {
-          let rotation = block.meta.get_rotation();
+          let orientation = block.orientation(&registry);
           if block.entity == Entity::from_bits(0) {
             Some(commands.spawn_empty())
           } else if skeleton_query.get(block.entity).is_ok() {
-            transform_query.get_mut(block.entity).unwrap().translation = from_ddd(i) + Vec3::new(0.5, 0.5, 0.5);
+            let mut transform = transform_query.get_mut(block.entity).unwrap();
+            transform.translation = from_ddd(i) + Vec3::new(0.5, 0.5, 0.5);
+            transform.rotation = orientation;
             None
           } else {
             Some(commands.entity(block.entity))
@@ -90,10 +100,7 @@ pub fn remesh_system_auxiliary(
             if let Some(mesh_assets_hash_map) = mesh_storage_assets.get(&storage.0) {
               let mut hash_map = HashMap::new();
               let id = commands
-                .insert(
-                  Transform::from_translation(from_ddd(i) + Vec3::new(0.5, 0.5, 0.5))
-                    .with_rotation(Quat::from_rotation_y(f32::PI() / 2.0 * rotation as i32 as f32)),
-                )
+                .insert(Transform::from_translation(from_ddd(i) + Vec3::new(0.5, 0.5, 0.5)).with_rotation(orientation))
                 .insert(GlobalTransform::default())
                 .with_children(|c| {
                   for (i, mesh_def) in skeleton.to_skeleton_def().skeleton {
@@ -0,0 +1,20 @@
+use crate::ecs::resources::player::BlockBreaking;
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use egui::Widget;
+
+// Progress bar under the crosshair while a block is being mined
+pub fn block_breaking(mut egui: ResMut<EguiContext>, window: Res<Windows>, block_breaking: Res<BlockBreaking>) {
+  if block_breaking.location.is_none() || !block_breaking.duration.is_finite() || block_breaking.duration <= 0.0 {
+    return;
+  }
+  let active_window = window.get_primary().unwrap();
+  let ui = egui.ctx_mut();
+  egui::Area::new("BlockBreaking")
+    .fixed_pos([active_window.width() / 2.0 - 100.0, active_window.height() / 2.0 + 30.0])
+    .show(ui, |ui| {
+      egui::ProgressBar::new(block_breaking.elapsed / block_breaking.duration)
+        .desired_width(200.0)
+        .ui(ui);
+    });
+}
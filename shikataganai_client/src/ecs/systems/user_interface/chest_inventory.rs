@@ -1,17 +1,18 @@
 use bevy::prelude::Entity;
 
-use crate::ecs::plugins::client::Requested;
+use crate::ecs::plugins::game::ShikataganaiGameState;
 use crate::ecs::plugins::rendering::inventory_pipeline::inventory_cache::ExtractedItems;
 use crate::ecs::plugins::rendering::inventory_pipeline::InventoryTextureOutputHandle;
 use crate::ecs::resources::player::PlayerInventory;
+use crate::ecs::systems::user_interface::{held_stack_tooltip, item_button_grid, player_grids, slot_clicked};
 use bevy::prelude::*;
 use bevy_egui::EguiContext;
 use bevy_renet::renet::RenetClient;
-use bincode::serialize;
-use egui::{emath, Color32, Id, TextStyle, Widget};
-use shikataganai_common::ecs::components::blocks::{QuantifiedBlockOrItem, ReverseLocation};
+use egui::emath;
+use iyes_loopless::prelude::NextState;
+use shikataganai_common::ecs::components::blocks::ReverseLocation;
 use shikataganai_common::ecs::components::functors::InternalInventory;
-use shikataganai_common::networking::{ClientChannel, FunctorType, PlayerCommand};
+use shikataganai_common::networking::{InventoryRef, InventorySlot};
 
 #[derive(Resource)]
 pub struct InventoryOpened(pub Entity);
@@ -20,16 +21,16 @@ pub struct InventoryOpened(pub Entity);
 pub enum InventoryItemMovementStatus {
   #[default]
   Nothing,
-  HoldingItemFrom(usize),
+  HoldingItemFrom(InventorySlot),
 }
 
 pub fn chest_inventory(
   mut commands: Commands,
   mut egui: ResMut<EguiContext>,
   window: Res<Windows>,
+  keys: Res<Input<KeyCode>>,
   inventory_opened: Option<ResMut<InventoryOpened>>,
   inventory_query: Query<&mut InternalInventory>,
-  requested_query: Query<&Requested>,
   location_query: Query<&ReverseLocation>,
   mut client: ResMut<RenetClient>,
   mut inventory_movement: ResMut<InventoryItemMovementStatus>,
@@ -41,79 +42,70 @@ pub fn chest_inventory(
   if let Some(inventory_entity) = inventory_opened.map(|e| e.0) {
     match inventory_query.get(inventory_entity) {
       Ok(internal_inventory) => {
+        let chest = InventoryRef::Block(location_query.get(inventory_entity).unwrap().0);
+        let content = |slot: InventorySlot| {
+          match slot.inventory {
+            InventoryRef::Player => player_inventory.items.get(slot.slot),
+            InventoryRef::Block(_) => internal_inventory.inventory.get(slot.slot),
+          }
+          .and_then(|stack| stack.as_ref())
+        };
+        let holding = match *inventory_movement {
+          InventoryItemMovementStatus::Nothing => None,
+          InventoryItemMovementStatus::HoldingItemFrom(from) => Some(from),
+        };
+        // The held stack is shown on the cursor instead of in its slot
+        let shown = |slot: InventorySlot| if holding == Some(slot) { None } else { content(slot) };
         let ui = egui.ctx_mut();
 
         egui::Window::new("Inventory")
           .title_bar(false)
           .resizable(false)
-          .fixed_pos(
-            [
-              active_window.width() / 2.0 - 1080.0 / 2.0,
-              active_window.height() - 600.0,
-            ],
-          )
+          .fixed_pos([
+            active_window.width() / 2.0 - 1080.0 / 2.0,
+            active_window.height() - 600.0,
+          ])
           .fixed_size([1080.0, 600.0])
           .show(ui, |ui| {
-            if let InventoryItemMovementStatus::HoldingItemFrom(from_slot) = *inventory_movement {
-              egui::popup::show_tooltip(ui.ctx(), Id::from("Tooltip"), |ui| {
-                let block_or_item = internal_inventory.inventory.get(from_slot).unwrap().as_ref().unwrap().block_or_item;
-                let coords = extracted_items.request(block_or_item).unwrap_or((0.0, 0.0));
-                egui::Image::new(inventory_texture.1, [95.0, 95.0]).uv([[coords.0, coords.1].into(), [coords.0 + 1.0 / 8.0, coords.1 + 1.0 / 8.0].into()]).ui(ui);
-              });
+            if let Some(stack) = holding.and_then(|from| content(from)) {
+              held_stack_tooltip(ui, stack, extracted_items.as_mut(), inventory_texture.as_ref());
             }
             ui.style_mut().spacing.button_padding = emath::Vec2::ZERO;
-            // let mut swap = None;
-            egui::Grid::new("Chest Inventory Grid").show(ui, |ui| {
-              for (i, item) in internal_inventory.inventory.iter().enumerate() {
-                if let InventoryItemMovementStatus::HoldingItemFrom(from_slot) = *inventory_movement && from_slot == i {
-                  egui::ImageButton::new(inventory_texture.1, [95.0, 95.0]).uv([[1.0, 1.1].into(), [1.0, 1.0].into()]).ui(ui);
-                } else {
-                  if match item {
-                    None => {
-                      egui::ImageButton::new(inventory_texture.1, [95.0, 95.0]).uv([[1.0, 1.1].into(), [1.0, 1.0].into()]).ui(ui)
-                    }
-                    Some(QuantifiedBlockOrItem { block_or_item, quant }) => {
-                      ui.allocate_ui([95.0, 95.0].into(), |ui| {
-                        let text = egui::WidgetText::RichText(egui::RichText::new(format!("{}", quant))).into_galley(ui, None, 50.0, TextStyle::Button);
-                        let coords = extracted_items.request(*block_or_item).unwrap_or((0.0, 0.0));
-                        let pos = ui.next_widget_position();
-                        let sense = egui::ImageButton::new(inventory_texture.1, [95.0, 95.0]).uv([[coords.0, coords.1].into(), [coords.0 + 1.0 / 8.0, coords.1 + 1.0 / 8.0].into()]).ui(ui);
-                        text.paint_with_fallback_color(ui.painter(), pos, Color32::WHITE);
-                        sense
-                      }).inner
-                    }
-                  }.clicked() {
-                    // match *item_move {
-                    //   ItemMove::Nothing => {
-                    //     if item.is_some() {
-                    //       *item_move = ItemMove::FromSlot(i);
-                    //     }
-                    //   }
-                    //   ItemMove::FromSlot(from_slot) => {
-                    //     swap = Some((from_slot, i));
-                    //   }
-                    // }
-                  }
-                }
-                if i % player_inventory.hot_bar_width == player_inventory.hot_bar_width - 1 {
-                  ui.end_row();
-                }
-              }
-            });
+            let slot_of = |slot| InventorySlot { inventory: chest, slot };
+            let mut clicked = item_button_grid(
+              "Chest Inventory Grid",
+              ui,
+              |slot| shown(slot_of(slot)),
+              0..internal_inventory.inventory.len(),
+              player_inventory.hot_bar_width,
+              extracted_items.as_mut(),
+              inventory_texture.as_ref(),
+            )
+            .map(slot_of);
+            ui.separator();
+            clicked = clicked.or(player_grids(
+              ui,
+              shown,
+              player_inventory.as_ref(),
+              extracted_items.as_mut(),
+              inventory_texture.as_ref(),
+            ));
+            if let Some(clicked) = clicked {
+              slot_clicked(
+                clicked,
+                content,
+                keys.pressed(KeyCode::LShift),
+                inventory_movement.as_mut(),
+                client.as_mut(),
+              );
+            }
           });
       }
       Err(_) => {
-        if !requested_query.get(inventory_entity).is_ok() {
-          let location = location_query.get(inventory_entity).unwrap();
-          client.send_message(
-            ClientChannel::ClientCommand.id(),
-            serialize(&PlayerCommand::RequestFunctor {
-              location: location.0,
-              functor: FunctorType::InternalInventory,
-            })
-            .unwrap(),
-          );
-          commands.entity(inventory_entity).insert(Requested);
+        // The block is gone, the server has dropped the subscription already. Otherwise its answer is still on the way
+        if location_query.get(inventory_entity).is_err() {
+          commands.remove_resource::<InventoryOpened>();
+          commands.insert_resource(NextState(ShikataganaiGameState::Simulation));
         }
       }
     }
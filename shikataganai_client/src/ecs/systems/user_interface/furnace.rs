@@ -0,0 +1,126 @@
+use crate::ecs::plugins::game::ShikataganaiGameState;
+use crate::ecs::plugins::rendering::inventory_pipeline::inventory_cache::ExtractedItems;
+use crate::ecs::plugins::rendering::inventory_pipeline::InventoryTextureOutputHandle;
+use crate::ecs::resources::player::PlayerInventory;
+use crate::ecs::systems::user_interface::chest_inventory::InventoryItemMovementStatus;
+use crate::ecs::systems::user_interface::{held_stack_tooltip, item_button, player_grids, slot_clicked};
+use bevy::prelude::*;
+use bevy_egui::EguiContext;
+use bevy_renet::renet::RenetClient;
+use egui::emath;
+use iyes_loopless::prelude::NextState;
+use shikataganai_common::ecs::components::blocks::ReverseLocation;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
+use shikataganai_common::networking::{InventoryRef, InventorySlot};
+
+#[derive(Resource)]
+pub struct FurnaceOpened(pub Entity);
+
+pub fn furnace_interface(
+  mut commands: Commands,
+  mut egui: ResMut<EguiContext>,
+  window: Res<Windows>,
+  keys: Res<Input<KeyCode>>,
+  furnace_opened: Option<Res<FurnaceOpened>>,
+  furnace_query: Query<(&InternalInventory, &Furnace)>,
+  location_query: Query<&ReverseLocation>,
+  mut client: ResMut<RenetClient>,
+  mut inventory_movement: ResMut<InventoryItemMovementStatus>,
+  mut extracted_items: ResMut<ExtractedItems>,
+  inventory_texture: Res<InventoryTextureOutputHandle>,
+  player_inventory: Res<PlayerInventory>,
+) {
+  let active_window = window.get_primary().unwrap();
+  if let Some(furnace_entity) = furnace_opened.map(|e| e.0) {
+    match furnace_query.get(furnace_entity) {
+      Ok((internal_inventory, furnace)) => {
+        let furnace_slots = InventoryRef::Block(location_query.get(furnace_entity).unwrap().0);
+        let content = |slot: InventorySlot| {
+          match slot.inventory {
+            InventoryRef::Player => player_inventory.items.get(slot.slot),
+            InventoryRef::Block(_) => internal_inventory.inventory.get(slot.slot),
+          }
+          .and_then(|stack| stack.as_ref())
+        };
+        let holding = match *inventory_movement {
+          InventoryItemMovementStatus::Nothing => None,
+          InventoryItemMovementStatus::HoldingItemFrom(from) => Some(from),
+        };
+        // The held stack is shown on the cursor instead of in its slot
+        let shown = |slot: InventorySlot| if holding == Some(slot) { None } else { content(slot) };
+        let ui = egui.ctx_mut();
+
+        egui::Window::new("Furnace")
+          .title_bar(false)
+          .resizable(false)
+          .fixed_pos([
+            active_window.width() / 2.0 - 1080.0 / 2.0,
+            active_window.height() - 600.0,
+          ])
+          .fixed_size([1080.0, 600.0])
+          .show(ui, |ui| {
+            if let Some(stack) = holding.and_then(|from| content(from)) {
+              held_stack_tooltip(ui, stack, extracted_items.as_mut(), inventory_texture.as_ref());
+            }
+            ui.style_mut().spacing.button_padding = emath::Vec2::ZERO;
+            let slot_of = |slot| InventorySlot {
+              inventory: furnace_slots,
+              slot,
+            };
+            let mut clicked = None;
+            let mut button = |ui: &mut egui::Ui, slot| {
+              if item_button(
+                ui,
+                shown(slot_of(slot)),
+                extracted_items.as_mut(),
+                inventory_texture.as_ref(),
+              )
+              .clicked()
+              {
+                clicked = Some(slot_of(slot));
+              }
+            };
+            ui.horizontal(|ui| {
+              ui.vertical(|ui| {
+                button(ui, Furnace::INPUT);
+                // What's left of the fuel that's burning
+                let burnt = if furnace.burn_total == 0 {
+                  0.0
+                } else {
+                  furnace.burn as f32 / furnace.burn_total as f32
+                };
+                ui.add(egui::ProgressBar::new(burnt).desired_width(95.0));
+                button(ui, Furnace::FUEL);
+              });
+              ui.add(egui::ProgressBar::new(furnace.progress as f32 / Furnace::COOK_TIME as f32).desired_width(200.0));
+              button(ui, Furnace::OUTPUT);
+            });
+            ui.separator();
+            clicked = clicked.or(player_grids(
+              ui,
+              shown,
+              player_inventory.as_ref(),
+              extracted_items.as_mut(),
+              inventory_texture.as_ref(),
+            ));
+            if let Some(clicked) = clicked {
+              slot_clicked(
+                clicked,
+                content,
+                keys.pressed(KeyCode::LShift),
+                inventory_movement.as_mut(),
+                client.as_mut(),
+              );
+            }
+          });
+      }
+      Err(_) => {
+        // The block is gone, the server has dropped the subscription already. Otherwise its answer is still on the way
+        if location_query.get(furnace_entity).is_err() {
+          commands.remove_resource::<FurnaceOpened>();
+          commands.insert_resource(NextState(ShikataganaiGameState::Simulation));
+        }
+      }
+    }
+  }
+}
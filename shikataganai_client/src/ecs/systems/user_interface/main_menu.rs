@@ -2,12 +2,14 @@ use crate::ecs::plugins::camera::Player;
 use crate::ecs::plugins::client::spawn_client;
 use crate::ecs::plugins::game::ShikataganaiGameState;
 use crate::ecs::plugins::settings::{AmbientOcclusion, FullScreen, MouseSensitivity, Resolution, VSync};
+use crate::ecs::resources::server::LocalServer;
 use bevy::app::AppExit;
 use bevy::prelude::*;
 use bevy_egui::*;
 use egui::Widget;
 use iyes_loopless::state::NextState;
 use shikataganai_server::ecs::plugins::server::ShikataganaiServerAddress;
+use shikataganai_server::ecs::resources::control::ServerControl;
 use shikataganai_server::spawn_server;
 use std::ops::{DerefMut, RangeInclusive};
 
@@ -28,6 +30,7 @@ pub fn main_menu(
   mut address_string: Local<LocalString<"IP">>,
   mut nickname_string: Local<LocalString<"Nickname">>,
   player_entity: Query<Entity, With<Player>>,
+  local_server: Option<Res<LocalServer>>,
 ) {
   let player_entity = player_entity.single();
   egui::Window::new("Main Menu").show(egui.ctx_mut(), |ui| {
@@ -43,7 +46,7 @@ pub fn main_menu(
     };
     if ui.button("Connect").clicked() {
       commands.insert_resource(NextState(ShikataganaiGameState::PreSimulation));
-      spawn_client(commands, player_entity, address.clone(), nickname);
+      spawn_client(&mut commands, player_entity, address.clone(), nickname);
     }
 
     egui::TextEdit::singleline(&mut address_string.deref_mut().0)
@@ -53,9 +56,20 @@ pub fn main_menu(
       .hint_text("Player")
       .show(ui);
 
-    if ui.button("Start Server").clicked() {
-      std::thread::spawn(|| {
-        spawn_server(ShikataganaiServerAddress { address });
+    if local_server.as_ref().is_some_and(|local_server| local_server.shutting_down) {
+      ui.label("Stopping the local server...");
+    } else if local_server.is_none() && ui.button("Start Server").clicked() {
+      let control = ServerControl::default();
+      let thread = {
+        let control = control.clone();
+        std::thread::spawn(move || {
+          spawn_server(ShikataganaiServerAddress { address }, control);
+        })
+      };
+      commands.insert_resource(LocalServer {
+        control,
+        thread: Some(thread),
+        shutting_down: false,
       });
     }
     if ui.button("Settings").clicked() {
@@ -1,11 +1,18 @@
 use crate::ecs::plugins::rendering::inventory_pipeline::inventory_cache::ExtractedItems;
 use crate::ecs::plugins::rendering::inventory_pipeline::InventoryTextureOutputHandle;
-use egui::{Color32, Response, Sense, TextStyle, Ui, Widget};
+use crate::ecs::resources::player::PlayerInventory;
+use crate::ecs::systems::user_interface::chest_inventory::InventoryItemMovementStatus;
+use bevy_renet::renet::RenetClient;
+use bincode::serialize;
+use egui::{Color32, Id, Response, Sense, TextStyle, Ui, Widget};
 use shikataganai_common::ecs::components::blocks::QuantifiedBlockOrItem;
+use shikataganai_common::networking::{ClientChannel, InventoryRef, InventorySlot, PlayerCommand};
 use std::ops::Range;
 
+pub mod block_breaking;
 pub mod chest_inventory;
 pub mod connecting;
+pub mod furnace;
 pub mod game_menu;
 pub mod hot_bar;
 pub mod main_menu;
@@ -77,3 +84,103 @@ where
   });
   clicked
 }
+
+// What dropping the held stack onto another slot asks the server for, holding shift splits off half of it
+fn slot_command(
+  from: InventorySlot,
+  to: InventorySlot,
+  stack: &QuantifiedBlockOrItem,
+  target: Option<&QuantifiedBlockOrItem>,
+  split: bool,
+) -> PlayerCommand {
+  if split {
+    PlayerCommand::SlotSplit {
+      from,
+      to,
+      quant: (stack.quant + 1) / 2,
+    }
+  } else if target.is_some_and(|target| target.block_or_item == stack.block_or_item) {
+    PlayerCommand::SlotMerge { from, to }
+  } else {
+    PlayerCommand::SlotMove { from, to }
+  }
+}
+
+// Shown next to the cursor while it's left out of its slot
+fn held_stack_tooltip(
+  ui: &Ui,
+  stack: &QuantifiedBlockOrItem,
+  extracted_items: &mut ExtractedItems,
+  inventory_texture: &InventoryTextureOutputHandle,
+) {
+  egui::popup::show_tooltip(ui.ctx(), Id::from("Tooltip"), |ui| {
+    let coords = extracted_items.request(stack.block_or_item).unwrap_or((0.0, 0.0));
+    egui::Image::new(inventory_texture.1, [95.0, 95.0])
+      .uv([
+        [coords.0, coords.1].into(),
+        [coords.0 + 1.0 / 8.0, coords.1 + 1.0 / 8.0].into(),
+      ])
+      .ui(ui);
+  });
+}
+
+// The rest of the player's inventory on top and the hot bar below it, the way interfaces show them under their own slots
+fn player_grids<'a>(
+  ui: &mut Ui,
+  shown: impl Fn(InventorySlot) -> Option<&'a QuantifiedBlockOrItem>,
+  player_inventory: &PlayerInventory,
+  extracted_items: &mut ExtractedItems,
+  inventory_texture: &InventoryTextureOutputHandle,
+) -> Option<InventorySlot> {
+  let slot = |slot| InventorySlot {
+    inventory: InventoryRef::Player,
+    slot,
+  };
+  let mut clicked = item_button_grid(
+    "Top Grid",
+    ui,
+    |x| shown(slot(x)),
+    player_inventory.hot_bar_width..player_inventory.items.len(),
+    player_inventory.hot_bar_width,
+    extracted_items,
+    inventory_texture,
+  );
+  ui.separator();
+  clicked = clicked.or(item_button_grid(
+    "Bottom Grid",
+    ui,
+    |x| shown(slot(x)),
+    0..player_inventory.hot_bar_width,
+    player_inventory.hot_bar_width,
+    extracted_items,
+    inventory_texture,
+  ));
+  clicked.map(slot)
+}
+
+// The first click picks up the stack of a slot, the second one asks the server to put it down on the clicked slot.
+// Nothing changes here until the server answers with what the slots hold
+fn slot_clicked<'a>(
+  clicked: InventorySlot,
+  content: impl Fn(InventorySlot) -> Option<&'a QuantifiedBlockOrItem>,
+  split: bool,
+  item_move: &mut InventoryItemMovementStatus,
+  client: &mut RenetClient,
+) {
+  match *item_move {
+    InventoryItemMovementStatus::Nothing => {
+      if content(clicked).is_some() {
+        *item_move = InventoryItemMovementStatus::HoldingItemFrom(clicked);
+      }
+    }
+    InventoryItemMovementStatus::HoldingItemFrom(from) => {
+      *item_move = InventoryItemMovementStatus::Nothing;
+      if let Some(stack) = content(from) {
+        if from != clicked {
+          let command = slot_command(from, clicked, stack, content(clicked), split);
+          client.send_message(ClientChannel::ClientCommand.id(), serialize(&command).unwrap());
+        }
+      }
+    }
+  }
+}
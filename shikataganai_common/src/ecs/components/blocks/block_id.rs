@@ -1,19 +1,23 @@
-use crate::ecs::components::blocks::{regular_blocks, regular_meshes, Block, BlockMeta, BlockTrait};
+use crate::ecs::components::blocks::{Block, BlockMeta};
 use bevy::prelude::Entity;
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
 
+// Index into the block registry, the same block can have different ids in different worlds
 #[derive(Copy, Clone, PartialEq, Debug, Eq, Hash, Serialize, Deserialize)]
-#[repr(u32)]
-pub enum BlockId {
-  Air,
-  Dirt,
-  Grass,
-  Cobble,
-  Iron,
-  Stair,
-  Chest,
-  Furnace,
+pub struct BlockId(pub u32);
+
+impl BlockId {
+  // Blocks of the core definitions, they're always registered first and keep these ids in every world
+  pub const AIR: BlockId = BlockId(0);
+  pub const DIRT: BlockId = BlockId(1);
+  pub const GRASS: BlockId = BlockId(2);
+  pub const COBBLE: BlockId = BlockId(3);
+  pub const IRON: BlockId = BlockId(4);
+  pub const STAIR: BlockId = BlockId(5);
+  pub const CHEST: BlockId = BlockId(6);
+  pub const FURNACE: BlockId = BlockId(7);
+  // Core blocks after these have no constant and may end up with other ids in worlds that had mods before them
+  pub const CONSTANT_IDS: u32 = 8;
 }
 
 impl Into<Block> for BlockId {
@@ -25,23 +29,3 @@ impl Into<Block> for BlockId {
     }
   }
 }
-
-static BLOCK_TRAITS: [&(dyn BlockTrait + Sync); 8] = [
-  &regular_blocks::Air,
-  &regular_blocks::Dirt,
-  &regular_blocks::Grass,
-  &regular_blocks::Cobblestone,
-  &regular_blocks::Iron,
-  &regular_meshes::Stair,
-  &regular_meshes::Chest,
-  &regular_blocks::Furnace,
-];
-
-impl Deref for BlockId {
-  type Target = dyn BlockTrait;
-
-  #[inline]
-  fn deref(&self) -> &'static Self::Target {
-    BLOCK_TRAITS[*self as usize]
-  }
-}
@@ -1,70 +1,23 @@
 use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
 use crate::ecs::components::item::ItemId;
 use crate::networking::BlockTransfer;
 use crate::util::array::DDD;
-use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::ops::Deref;
 
 pub mod animation;
 pub mod block_id;
-pub mod regular_blocks;
-pub mod regular_meshes;
-
-pub trait BlockTrait {
-  fn visible(&self) -> bool {
-    true
-  }
-  fn passable(&self) -> bool {
-    false
-  }
-  fn need_to_spawn_functors(&self) -> bool {
-    false
-  } // Can be done better ? ? ?
-  fn spawn_functors(&self, _location: DDD, _commands: &mut EntityCommands) {}
-  fn spawn_or_add_functors(&self, block: &mut Block, location: DDD, commands: &mut Commands) {
-    let mut commands = if block.entity == Entity::from_bits(0) {
-      commands.spawn_empty()
-    } else {
-      commands.entity(block.entity)
-    };
-    self.spawn_functors(location, &mut commands);
-    block.entity = commands.id();
-  }
-  fn need_reverse_location(&self) -> bool {
-    false
-  }
-  // fn render_info(&self) -> BlockRenderInfo;
-}
-
-pub enum BlockRotation {
-  NORTH,
-  EAST,
-  SOUTH,
-  WEST,
-}
+pub mod registry;
+pub mod state;
+pub mod tick;
 
+// Packed block-state properties, go through Block::get and Block::set to read them
 #[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct BlockMeta {
   pub v: u32,
 }
 
-impl BlockMeta {
-  pub fn get_rotation(self) -> BlockRotation {
-    match self.v % 4 {
-      0 => BlockRotation::NORTH,
-      1 => BlockRotation::EAST,
-      2 => BlockRotation::SOUTH,
-      3 => BlockRotation::WEST,
-      _ => panic!("Shouldn't happen"),
-    }
-  }
-  pub fn set_rotation(&mut self, rotation: BlockRotation) {
-    self.v = (self.v ^ (self.v & 0b11)) | rotation as u32;
-  }
-}
-
 #[derive(Debug, Component, Copy, Clone, Serialize, Deserialize)]
 pub struct Block {
   pub block: BlockId,
@@ -101,22 +54,30 @@ impl Block {
   }
 }
 
-impl Deref for Block {
-  type Target = dyn BlockTrait;
-
-  #[inline]
-  fn deref(&self) -> &Self::Target {
-    self.block.deref()
-  }
-}
-
 #[derive(Component, Copy, Clone, Debug, Eq, Hash, PartialEq, Serialize, Deserialize)]
 pub enum BlockOrItem {
   Block(BlockId),
   Item(ItemId),
 }
 
-#[derive(Serialize, Deserialize)]
+impl BlockOrItem {
+  // What a furnace turns it into, only blocks have that in their definition
+  pub fn smelt(&self, registry: &BlockRegistry) -> Option<QuantifiedBlockOrItem> {
+    match self {
+      BlockOrItem::Block(block) => registry.get(*block).smelt(registry),
+      BlockOrItem::Item(_) => None,
+    }
+  }
+
+  pub fn burn_time(&self) -> Option<u32> {
+    match self {
+      BlockOrItem::Block(_) => None,
+      BlockOrItem::Item(item) => item.burn_time(),
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct QuantifiedBlockOrItem {
   pub block_or_item: BlockOrItem,
   pub quant: u32,
@@ -0,0 +1,476 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::state::{property_bits, property_values};
+use crate::ecs::components::blocks::tick::{fall, pop, unsupported, TickBehaviour, TickKind, TickOutcome, FALL_DELAY};
+use crate::ecs::components::blocks::{Block, BlockOrItem, QuantifiedBlockOrItem, ReverseLocation};
+use crate::ecs::components::functors::FunctorDef;
+use crate::ecs::components::item::{ItemId, ToolKind};
+use crate::ecs::resources::world::GameWorld;
+use crate::util::array::DDD;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+// Mods put their definition files here, they're read in file name order after the core definitions
+pub const BLOCK_DEFINITIONS_DIR: &str = "blocks";
+const CORE_DEFINITIONS: &str = include_str!("core.toml");
+
+pub const MAX_EMISSION: u8 = 15;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BlockRender {
+  #[default]
+  Nothing,
+  Cube {
+    textures: [u32; 6],
+  },
+  Mesh {
+    mesh: String,
+  },
+  Skeleton {
+    skeleton: String,
+  },
+}
+
+// Which pass draws the cube faces of a block
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderLayer {
+  #[default]
+  Opaque,
+  // Pixels with little alpha get discarded, like leaves or bars
+  Cutout,
+  // Blended over whatever is behind, like glass or water
+  Translucent,
+}
+
+impl RenderLayer {
+  pub const ALL: [RenderLayer; 3] = [RenderLayer::Opaque, RenderLayer::Cutout, RenderLayer::Translucent];
+}
+
+fn default_opaque() -> bool {
+  true
+}
+
+fn default_hardness() -> f32 {
+  1.0
+}
+
+fn default_quant() -> u32 {
+  1
+}
+
+fn default_chance() -> f64 {
+  1.0
+}
+
+// One entry of a drop table, gives either a block or an item
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockDrop {
+  // Kept as a name, definitions get read before the registry that resolves it exists
+  #[serde(default)]
+  pub block: Option<String>,
+  #[serde(default)]
+  pub item: Option<ItemId>,
+  #[serde(default = "default_quant")]
+  pub quant: u32,
+  #[serde(default = "default_chance")]
+  pub chance: f64,
+}
+
+impl BlockDrop {
+  // `owner` is the block whose definition has the entry, for the error messages
+  pub fn resolve(&self, owner: &str, registry: &BlockRegistry) -> Option<QuantifiedBlockOrItem> {
+    let block_or_item = match (&self.block, self.item) {
+      (Some(name), None) => match registry.id(name) {
+        Some(block) => BlockOrItem::Block(block),
+        None => {
+          println!("Block {} names unknown block {}", owner, name);
+          return None;
+        }
+      },
+      (None, Some(item)) => BlockOrItem::Item(item),
+      _ => {
+        println!("Entry of block {} has to name either a block or an item", owner);
+        return None;
+      }
+    };
+    Some(QuantifiedBlockOrItem {
+      block_or_item,
+      quant: self.quant,
+    })
+  }
+}
+
+// One [[block]] table of a definition file, see core.toml for the fields
+#[derive(Clone, Debug, Deserialize)]
+pub struct BlockDef {
+  pub name: String,
+  // Blocks light, definitions from before it was split up call it visible
+  #[serde(default = "default_opaque", alias = "visible")]
+  pub opaque: bool,
+  // Hides the faces of the neighbours touching it, same as opaque if missing
+  #[serde(default)]
+  pub occludes: Option<bool>,
+  #[serde(default)]
+  pub layer: RenderLayer,
+  #[serde(default)]
+  pub passable: bool,
+  // Falls down until it lands on something that isn't passable
+  #[serde(default)]
+  pub gravity: bool,
+  // Pops off once the block below is gone
+  #[serde(default)]
+  pub needs_support: bool,
+  // Hearth light the block gives off, up to MAX_EMISSION
+  #[serde(default)]
+  pub emission: u8,
+  #[serde(default = "default_hardness")]
+  pub hardness: f32,
+  #[serde(default)]
+  pub tool: Option<ToolKind>,
+  // Missing means the block drops itself
+  #[serde(default)]
+  pub drops: Option<Vec<BlockDrop>>,
+  // What a furnace turns it into, the chance is ignored
+  #[serde(default)]
+  pub smelts: Option<BlockDrop>,
+  // Block-state properties like "facing" or "age", packed into the meta in this order
+  #[serde(default)]
+  pub properties: Vec<String>,
+  #[serde(default)]
+  pub tick: Option<TickBehaviour>,
+  #[serde(default)]
+  pub render: BlockRender,
+  #[serde(default)]
+  pub functors: Vec<FunctorDef>,
+  #[serde(default)]
+  pub interface: Option<String>,
+}
+
+impl BlockDef {
+  // Stands in for blocks the world has ids for but no definition file provides anymore
+  fn placeholder(name: &str) -> Self {
+    Self {
+      name: name.to_string(),
+      opaque: true,
+      occludes: None,
+      layer: RenderLayer::Opaque,
+      passable: false,
+      gravity: false,
+      needs_support: false,
+      emission: 0,
+      hardness: default_hardness(),
+      tool: None,
+      drops: None,
+      smelts: None,
+      properties: vec![],
+      tick: None,
+      render: BlockRender::Cube { textures: [0; 6] },
+      functors: vec![],
+      interface: None,
+    }
+  }
+
+  pub fn opaque(&self) -> bool {
+    self.opaque
+  }
+
+  pub fn occludes(&self) -> bool {
+    self.occludes.unwrap_or(self.opaque)
+  }
+
+  pub fn passable(&self) -> bool {
+    self.passable
+  }
+
+  // Seconds it takes to break with the tool in hand, blocks with negative hardness can't be broken
+  pub fn break_time(&self, tool: Option<ItemId>) -> f32 {
+    if self.hardness < 0.0 {
+      return f32::INFINITY;
+    }
+    match tool.and_then(|item| item.tool()) {
+      Some((kind, speed)) if self.tool == Some(kind) => self.hardness / speed,
+      _ => self.hardness,
+    }
+  }
+
+  // `random` gives a uniform roll in [0, 1) for every entry of the table
+  pub fn roll_drops(&self, registry: &BlockRegistry, mut random: impl FnMut() -> f64) -> Vec<QuantifiedBlockOrItem> {
+    let drops = match &self.drops {
+      None => {
+        return vec![QuantifiedBlockOrItem {
+          block_or_item: BlockOrItem::Block(registry.id(&self.name).unwrap()),
+          quant: 1,
+        }]
+      }
+      Some(drops) => drops,
+    };
+    let mut rolled = vec![];
+    for drop in drops {
+      if random() >= drop.chance {
+        continue;
+      }
+      if let Some(drop) = drop.resolve(&self.name, registry) {
+        rolled.push(drop);
+      }
+    }
+    rolled
+  }
+
+  pub fn smelt(&self, registry: &BlockRegistry) -> Option<QuantifiedBlockOrItem> {
+    self
+      .smelts
+      .as_ref()
+      .and_then(|smelts| smelts.resolve(&self.name, registry))
+  }
+
+  // Shift and width of the property's bits in the meta
+  pub fn property_slot(&self, name: &str) -> Option<(u32, u32)> {
+    let mut shift = 0;
+    for property in self.properties.iter() {
+      let bits = property_bits(property_values(property)?);
+      if property == name {
+        return Some((shift, bits));
+      }
+      shift += bits;
+    }
+    None
+  }
+
+  // Only blocks that do something get picked for random ticks
+  pub fn ticks(&self) -> bool {
+    self.tick.is_some()
+  }
+
+  // Server side hook for random and scheduled ticks, `block` is the one at `location`
+  pub fn on_tick(
+    &self,
+    kind: TickKind,
+    block: Block,
+    location: DDD,
+    game_world: &GameWorld,
+    registry: &BlockRegistry,
+    random: impl FnMut() -> f64,
+  ) -> TickOutcome {
+    if kind == TickKind::Scheduled && unsupported(location, game_world, registry) {
+      if self.gravity {
+        return fall(block, location, game_world);
+      }
+      // Another go at popping off after nobody could take what it leaves behind
+      if self.needs_support {
+        return pop(location);
+      }
+    }
+    match &self.tick {
+      None => TickOutcome::default(),
+      Some(tick) => tick.tick(block, location, game_world, registry, random),
+    }
+  }
+
+  // Server side hook for when the block at `changed` got replaced, that's one of the immediate neighbours or the
+  // block itself right after it was placed
+  pub fn on_neighbour_changed(
+    &self,
+    _block: Block,
+    location: DDD,
+    _changed: DDD,
+    game_world: &GameWorld,
+    registry: &BlockRegistry,
+  ) -> TickOutcome {
+    let mut outcome = TickOutcome::default();
+    if !unsupported(location, game_world, registry) {
+      return outcome;
+    }
+    if self.needs_support {
+      return pop(location);
+    } else if self.gravity {
+      outcome.schedule.push((location, FALL_DELAY));
+    }
+    outcome
+  }
+
+  pub fn need_to_spawn_functors(&self) -> bool {
+    !self.functors.is_empty()
+  }
+
+  pub fn spawn_functors(&self, location: DDD, commands: &mut EntityCommands) {
+    commands.insert(ReverseLocation(location));
+    for functor in self.functors.iter() {
+      functor.insert(commands);
+    }
+  }
+
+  pub fn spawn_or_add_functors(&self, block: &mut Block, location: DDD, commands: &mut Commands) {
+    let mut commands = if block.entity == Entity::from_bits(0) {
+      commands.spawn_empty()
+    } else {
+      commands.entity(block.entity)
+    };
+    self.spawn_functors(location, &mut commands);
+    block.entity = commands.id();
+  }
+
+  // Interfaces find the block of a functor entity through its ReverseLocation
+  pub fn need_reverse_location(&self) -> bool {
+    !self.functors.is_empty()
+  }
+}
+
+#[derive(Deserialize)]
+struct DefinitionFile {
+  #[serde(default)]
+  block: Vec<BlockDef>,
+}
+
+fn parse_definitions(str: &str) -> Result<Vec<BlockDef>, String> {
+  let mut blocks = toml::from_str::<DefinitionFile>(str)
+    .map(|file| file.block)
+    .map_err(|err| err.to_string())?;
+  for block in blocks.iter_mut() {
+    block.emission = block.emission.min(MAX_EMISSION);
+    let mut bits = 0;
+    let name = block.name.clone();
+    block.properties.retain(|property| match property_values(property) {
+      None => {
+        println!("Block {} has unknown property {}", name, property);
+        false
+      }
+      Some(values) if bits + property_bits(values) > u32::BITS => {
+        println!("Property {} doesn't fit into the meta of block {}", property, name);
+        false
+      }
+      Some(values) => {
+        bits += property_bits(values);
+        true
+      }
+    });
+  }
+  Ok(blocks)
+}
+
+pub fn core_definitions() -> Vec<BlockDef> {
+  parse_definitions(CORE_DEFINITIONS).unwrap()
+}
+
+// Core definitions followed by the files in the directory, a definition with a name that's already taken replaces the old one
+pub fn load_definitions(dir: &Path) -> Vec<BlockDef> {
+  let mut definitions = core_definitions();
+  let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+    Ok(entries) => entries
+      .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+      .filter(|path| path.extension().is_some_and(|extension| extension == "toml"))
+      .collect(),
+    Err(_) => vec![],
+  };
+  paths.sort();
+  for path in paths {
+    match std::fs::read_to_string(&path)
+      .map_err(|err| err.to_string())
+      .and_then(|str| parse_definitions(&str))
+    {
+      Ok(blocks) => {
+        for block in blocks {
+          match definitions
+            .iter_mut()
+            .find(|definition| definition.name.eq_ignore_ascii_case(&block.name))
+          {
+            Some(definition) => *definition = block,
+            None => definitions.push(block),
+          }
+        }
+      }
+      Err(err) => println!("Failed to load block definitions {}: {}", path.display(), err),
+    }
+  }
+  definitions
+}
+
+// Every app has its own, the server's comes from its world and the client gets the server's names when it joins.
+// Clones are cheap and share the definitions, chunk tasks take one along.
+#[derive(Resource, Clone)]
+pub struct BlockRegistry {
+  blocks: Arc<Vec<BlockDef>>,
+  // Lowercase names, block names are matched without case everywhere
+  ids: Arc<HashMap<String, BlockId>>,
+}
+
+impl BlockRegistry {
+  // Names in `known` keep their position as id, definitions that aren't among them get the ids after that.
+  pub fn assign(definitions: Vec<BlockDef>, known: &[String]) -> Self {
+    let mut blocks: Vec<BlockDef> = known
+      .iter()
+      .map(|name| {
+        match definitions
+          .iter()
+          .find(|definition| definition.name.eq_ignore_ascii_case(name))
+        {
+          Some(definition) => definition.clone(),
+          None => {
+            println!("No definition for block {}, it stays as a placeholder", name);
+            BlockDef::placeholder(name)
+          }
+        }
+      })
+      .collect();
+    for definition in definitions {
+      if !blocks
+        .iter()
+        .any(|block| block.name.eq_ignore_ascii_case(&definition.name))
+      {
+        blocks.push(definition);
+      }
+    }
+    let ids = blocks
+      .iter()
+      .enumerate()
+      .map(|(id, block)| (block.name.to_lowercase(), BlockId(id as u32)))
+      .collect();
+    let registry = Self {
+      blocks: Arc::new(blocks),
+      ids: Arc::new(ids),
+    };
+    for (id, definition) in core_definitions()
+      .iter()
+      .enumerate()
+      .take(BlockId::CONSTANT_IDS as usize)
+    {
+      assert_eq!(
+        registry.id(&definition.name),
+        Some(BlockId(id as u32)),
+        "Core block {} lost its id",
+        definition.name
+      );
+    }
+    registry
+  }
+
+  #[inline]
+  pub fn get(&self, block: BlockId) -> &BlockDef {
+    &self.blocks[block.0 as usize]
+  }
+
+  pub fn id(&self, name: &str) -> Option<BlockId> {
+    self.ids.get(&name.to_lowercase()).copied()
+  }
+
+  // Indexed by id
+  pub fn definitions(&self) -> &[BlockDef] {
+    &self.blocks
+  }
+
+  // Indexed by id, this is what worlds store and clients get sent
+  pub fn names(&self) -> Vec<String> {
+    self.blocks.iter().map(|block| block.name.clone()).collect()
+  }
+}
+
+// Only the core blocks, until there's a world or a server to take the ids from
+impl Default for BlockRegistry {
+  fn default() -> Self {
+    Self::assign(core_definitions(), &[])
+  }
+}
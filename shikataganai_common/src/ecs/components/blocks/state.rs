@@ -0,0 +1,230 @@
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::blocks::Block;
+use bevy::prelude::*;
+use std::f32::consts::{FRAC_PI_2, PI};
+
+// Value of a named block-state property. Blocks list the properties they have in their definition and the values
+// get packed into the meta bits in that order, each taking as many bits as its values need.
+pub trait BlockProperty: Copy {
+  const NAME: &'static str;
+  const VALUES: u32;
+
+  fn index(self) -> u32;
+  fn from_index(index: u32) -> Self;
+}
+
+// Value count of every property definitions can use
+pub fn property_values(name: &str) -> Option<u32> {
+  match name {
+    Facing::NAME => Some(Facing::VALUES),
+    Half::NAME => Some(Half::VALUES),
+    Open::NAME => Some(Open::VALUES),
+    Age::NAME => Some(Age::VALUES),
+    _ => None,
+  }
+}
+
+pub fn property_bits(values: u32) -> u32 {
+  u32::BITS - (values - 1).leading_zeros()
+}
+
+// The horizontal ones come first, so metas from when only those existed read the same
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Facing {
+  North,
+  East,
+  South,
+  West,
+  Up,
+  Down,
+}
+
+impl BlockProperty for Facing {
+  const NAME: &'static str = "facing";
+  const VALUES: u32 = 6;
+
+  fn index(self) -> u32 {
+    self as u32
+  }
+
+  fn from_index(index: u32) -> Self {
+    match index {
+      0 => Facing::North,
+      1 => Facing::East,
+      2 => Facing::South,
+      3 => Facing::West,
+      4 => Facing::Up,
+      _ => Facing::Down,
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Half {
+  Bottom,
+  Top,
+}
+
+impl BlockProperty for Half {
+  const NAME: &'static str = "half";
+  const VALUES: u32 = 2;
+
+  fn index(self) -> u32 {
+    self as u32
+  }
+
+  fn from_index(index: u32) -> Self {
+    if index == 0 {
+      Half::Bottom
+    } else {
+      Half::Top
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Open(pub bool);
+
+impl BlockProperty for Open {
+  const NAME: &'static str = "open";
+  const VALUES: u32 = 2;
+
+  fn index(self) -> u32 {
+    self.0 as u32
+  }
+
+  fn from_index(index: u32) -> Self {
+    Open(index != 0)
+  }
+}
+
+// Growth stage, 0 to 7
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Age(pub u8);
+
+impl BlockProperty for Age {
+  const NAME: &'static str = "age";
+  const VALUES: u32 = 8;
+
+  fn index(self) -> u32 {
+    self.0.min(7) as u32
+  }
+
+  fn from_index(index: u32) -> Self {
+    Age(index as u8)
+  }
+}
+
+impl Block {
+  // None if the block doesn't have the property
+  pub fn get<P: BlockProperty>(&self, registry: &BlockRegistry) -> Option<P> {
+    let (shift, bits) = registry.get(self.block).property_slot(P::NAME)?;
+    Some(P::from_index((self.meta.v >> shift) & ((1 << bits) - 1)))
+  }
+
+  pub fn set<P: BlockProperty>(&mut self, registry: &BlockRegistry, value: P) -> Option<()> {
+    let (shift, bits) = registry.get(self.block).property_slot(P::NAME)?;
+    let mask = ((1 << bits) - 1) << shift;
+    self.meta.v = (self.meta.v & !mask) | ((value.index() << shift) & mask);
+    Some(())
+  }
+
+  // Metas coming from clients can hold anything. Keeps the values of the block's own properties that are in range,
+  // the rest go back to their first value and the bits no property uses get cleared.
+  pub fn sanitize_meta(&mut self, registry: &BlockRegistry) {
+    let definition = registry.get(self.block);
+    let mut meta = 0;
+    for property in definition.properties.iter() {
+      let (shift, bits) = definition.property_slot(property).unwrap();
+      let value = (self.meta.v >> shift) & ((1 << bits) - 1);
+      if value < property_values(property).unwrap() {
+        meta |= value << shift;
+      }
+    }
+    self.meta.v = meta;
+  }
+
+  // How meshes and block faces get turned, they're modelled facing north and standing on their bottom half
+  pub fn orientation(&self, registry: &BlockRegistry) -> Quat {
+    let facing = match self.get::<Facing>(registry) {
+      None | Some(Facing::North) => Quat::IDENTITY,
+      Some(Facing::East) => Quat::from_rotation_y(FRAC_PI_2),
+      Some(Facing::South) => Quat::from_rotation_y(PI),
+      Some(Facing::West) => Quat::from_rotation_y(-FRAC_PI_2),
+      Some(Facing::Up) => Quat::from_rotation_z(FRAC_PI_2),
+      Some(Facing::Down) => Quat::from_rotation_z(-FRAC_PI_2),
+    };
+    match self.get::<Half>(registry) {
+      Some(Half::Top) => facing * Quat::from_rotation_x(PI),
+      _ => facing,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ecs::components::blocks::block_id::BlockId;
+
+  #[test]
+  fn bits_fit_the_values() {
+    assert_eq!(property_bits(1), 0);
+    assert_eq!(property_bits(Half::VALUES), 1);
+    assert_eq!(property_bits(Facing::VALUES), 3);
+    assert_eq!(property_bits(Age::VALUES), 3);
+    assert_eq!(property_bits(9), 4);
+  }
+
+  #[test]
+  fn properties_pack_side_by_side() {
+    let registry = BlockRegistry::default();
+    // Stairs have facing in the lowest three bits and half right after it
+    let mut stair = Block::new(BlockId::STAIR);
+    assert_eq!(stair.get::<Facing>(&registry), Some(Facing::North));
+    assert_eq!(stair.get::<Half>(&registry), Some(Half::Bottom));
+    stair.set(&registry, Facing::West).unwrap();
+    stair.set(&registry, Half::Top).unwrap();
+    assert_eq!(stair.meta.v, 0b1011);
+    assert_eq!(stair.get::<Facing>(&registry), Some(Facing::West));
+    assert_eq!(stair.get::<Half>(&registry), Some(Half::Top));
+    stair.set(&registry, Facing::Down).unwrap();
+    assert_eq!(stair.get::<Facing>(&registry), Some(Facing::Down));
+    assert_eq!(stair.get::<Half>(&registry), Some(Half::Top));
+  }
+
+  #[test]
+  fn horizontal_metas_read_the_same() {
+    let registry = BlockRegistry::default();
+    for facing in [Facing::North, Facing::East, Facing::South, Facing::West] {
+      let mut chest = Block::new(BlockId::CHEST);
+      chest.meta.v = facing as u32;
+      assert_eq!(chest.get::<Facing>(&registry), Some(facing));
+    }
+  }
+
+  #[test]
+  fn missing_properties_are_left_alone() {
+    let registry = BlockRegistry::default();
+    let mut cobble = Block::new(BlockId::COBBLE);
+    assert_eq!(cobble.get::<Facing>(&registry), None);
+    assert_eq!(cobble.set(&registry, Age(3)), None);
+    assert_eq!(cobble.meta.v, 0);
+  }
+
+  #[test]
+  fn client_metas_get_sanitized() {
+    let registry = BlockRegistry::default();
+    // Facing 7 doesn't exist, half is top and everything above it belongs to nothing
+    let mut stair = Block::new(BlockId::STAIR);
+    stair.meta.v = 0b1101_1111;
+    stair.sanitize_meta(&registry);
+    assert_eq!(stair.get::<Facing>(&registry), Some(Facing::North));
+    assert_eq!(stair.get::<Half>(&registry), Some(Half::Top));
+    assert_eq!(stair.meta.v, 0b1000);
+
+    let mut cobble = Block::new(BlockId::COBBLE);
+    cobble.meta.v = 0b101;
+    cobble.sanitize_meta(&registry);
+    assert_eq!(cobble.meta.v, 0);
+  }
+}
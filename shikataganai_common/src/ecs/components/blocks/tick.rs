@@ -0,0 +1,302 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::blocks::state::{Age, BlockProperty};
+use crate::ecs::components::blocks::Block;
+use crate::ecs::resources::world::GameWorld;
+use crate::util::array::DDD;
+use serde::Deserialize;
+
+// Light a block needs above it for something to spread onto it
+pub const SPREAD_LIGHT: u8 = 9;
+// Server ticks a gravity block waits before it falls another block
+pub const FALL_DELAY: u32 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TickKind {
+  // The server picks a few blocks of every loaded section each tick
+  Random,
+  // Asked for some ticks earlier, see ScheduledTicks on the server
+  Scheduled,
+}
+
+fn default_grow_chance() -> f64 {
+  1.0
+}
+
+// What a block does when it gets ticked, e.g. tick = { type = "spread", onto = "dirt", decay = "dirt" }
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum TickBehaviour {
+  // Turns a nearby `onto` block with enough light above it into this block, turns into `decay` once covered up
+  Spread {
+    onto: String,
+    #[serde(default)]
+    decay: Option<String>,
+  },
+  // Steps the age property up to its last value, needs "age" among the properties
+  Grow {
+    #[serde(default = "default_grow_chance")]
+    chance: f64,
+  },
+}
+
+// What the server applies and sends out after a tick
+#[derive(Default)]
+pub struct TickOutcome {
+  pub changes: Vec<(DDD, Block)>,
+  // Locations to tick again after that many server ticks
+  pub schedule: Vec<(DDD, u32)>,
+  // Changes at these locations broke the block off, it leaves behind its drops and whatever it holds like on a break
+  pub popped: Vec<DDD>,
+}
+
+impl TickBehaviour {
+  // `random` gives uniform rolls in [0, 1)
+  pub fn tick(
+    &self,
+    block: Block,
+    location: DDD,
+    game_world: &GameWorld,
+    registry: &BlockRegistry,
+    mut random: impl FnMut() -> f64,
+  ) -> TickOutcome {
+    let mut outcome = TickOutcome::default();
+    match self {
+      TickBehaviour::Spread { onto, decay } => {
+        let above = |c: DDD| {
+          game_world
+            .get((c.0, c.1 + 1, c.2))
+            .is_some_and(|block| registry.get(block.block).opaque())
+        };
+        if above(location) {
+          // Names that aren't in the registry just do nothing, they'd get reported on every tick otherwise
+          if let Some(decay) = decay.as_ref().and_then(|name| registry.id(name)) {
+            outcome.changes.push((location, Block::new(decay)));
+          }
+          return outcome;
+        }
+        let onto = match registry.id(onto) {
+          None => return outcome,
+          Some(onto) => onto,
+        };
+        let offset = |random: f64, span: i32| (random * span as f64) as i32 - span / 2;
+        let target = (
+          location.0 + offset(random(), 3),
+          location.1 + offset(random(), 3),
+          location.2 + offset(random(), 3),
+        );
+        let lit = game_world
+          .get_light_level((target.0, target.1 + 1, target.2))
+          .is_some_and(|light_level| light_level.heaven.max(light_level.hearth) >= SPREAD_LIGHT);
+        if game_world.get(target).is_some_and(|block| block.block == onto) && lit && !above(target) {
+          outcome.changes.push((target, Block::new(block.block)));
+        }
+      }
+      TickBehaviour::Grow { chance } => {
+        if let Some(Age(age)) = block.get::<Age>(registry) && age as u32 + 1 < Age::VALUES && random() < *chance {
+          let mut grown = block;
+          grown.set(registry, Age(age + 1));
+          outcome.changes.push((location, grown));
+        }
+      }
+    }
+    outcome
+  }
+}
+
+// Nothing to rest on, the bottom of the world and unloaded chunks hold blocks up
+pub fn unsupported(location: DDD, game_world: &GameWorld, registry: &BlockRegistry) -> bool {
+  game_world
+    .get((location.0, location.1 - 1, location.2))
+    .is_some_and(|below| registry.get(below.block).passable())
+}
+
+// Breaks the block off where it is, it leaves behind its drops and whatever it holds like on a break
+pub fn pop(location: DDD) -> TickOutcome {
+  TickOutcome {
+    changes: vec![(location, Block::new(BlockId::AIR))],
+    schedule: vec![],
+    popped: vec![location],
+  }
+}
+
+// Moves the block down by one, the block that lands gets a block update and falls on from there. Whatever passable
+// block it lands in, like a torch, breaks off as if it was broken.
+pub fn fall(block: Block, location: DDD, game_world: &GameWorld) -> TickOutcome {
+  let below = (location.0, location.1 - 1, location.2);
+  let popped = if game_world.get(below).is_some_and(|below| below.block != BlockId::AIR) {
+    vec![below]
+  } else {
+    vec![]
+  };
+  TickOutcome {
+    changes: vec![(location, Block::new(BlockId::AIR)), (below, block)],
+    schedule: vec![],
+    popped,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ecs::components::chunk::{Chunk, DEFAULT_WORLD_HEIGHT};
+
+  // Dirt up to y 10 and air above, `f` puts other blocks in between
+  fn world(f: impl Fn(DDD) -> Option<BlockId>) -> GameWorld {
+    let mut game_world = GameWorld::default();
+    let chunk = Chunk::new((0, 0), DEFAULT_WORLD_HEIGHT, &BlockRegistry::default(), |c| {
+      f(c).unwrap_or(if c.1 <= 10 { BlockId::DIRT } else { BlockId::AIR })
+    });
+    game_world.insert_chunk((0, 0), chunk);
+    game_world
+  }
+
+  fn changes(outcome: &TickOutcome) -> Vec<(DDD, BlockId)> {
+    outcome.changes.iter().map(|(c, block)| (*c, block.block)).collect()
+  }
+
+  // Rolls that make grass at (5, 10, 5) pick the block at `target`, one block away at most on every axis
+  fn rolls(target: DDD) -> impl FnMut() -> f64 {
+    let mut rolls = [target.0 - 5, target.1 - 10, target.2 - 5]
+      .into_iter()
+      .map(|offset| (offset + 1) as f64 / 3.0 + 0.1);
+    move || rolls.next().unwrap()
+  }
+
+  fn grass(world: &GameWorld, target: DDD) -> Vec<(DDD, BlockId)> {
+    let registry = BlockRegistry::default();
+    let grass = registry.get(BlockId::GRASS);
+    let outcome = grass.on_tick(
+      TickKind::Random,
+      Block::new(BlockId::GRASS),
+      (5, 10, 5),
+      world,
+      &registry,
+      rolls(target),
+    );
+    changes(&outcome)
+  }
+
+  fn tick(world: &GameWorld, kind: TickKind, block: BlockId, location: DDD) -> TickOutcome {
+    let registry = BlockRegistry::default();
+    registry
+      .get(block)
+      .on_tick(kind, Block::new(block), location, world, &registry, || 0.0)
+  }
+
+  fn neighbour_changed(world: &GameWorld, block: BlockId, location: DDD, changed: DDD) -> TickOutcome {
+    let registry = BlockRegistry::default();
+    registry
+      .get(block)
+      .on_neighbour_changed(Block::new(block), location, changed, world, &registry)
+  }
+
+  #[test]
+  fn grass_spreads_onto_lit_dirt() {
+    let world = world(|c| (c == (5, 10, 5)).then_some(BlockId::GRASS));
+    assert_eq!(grass(&world, (6, 10, 5)), vec![((6, 10, 5), BlockId::GRASS)]);
+    assert_eq!(grass(&world, (4, 9, 6)), vec![]);
+    assert_eq!(grass(&world, (5, 11, 5)), vec![]);
+  }
+
+  #[test]
+  fn grass_needs_light_to_spread() {
+    // A roof over the whole chunk, the dirt under it stays dark
+    let dark = world(|c| match c {
+      (5, 10, 5) => Some(BlockId::GRASS),
+      (_, 12, _) => Some(BlockId::COBBLE),
+      _ => None,
+    });
+    assert_eq!(grass(&dark, (6, 10, 5)), vec![]);
+    let covered = world(|c| match c {
+      (5, 10, 5) => Some(BlockId::GRASS),
+      (6, 11, 5) => Some(BlockId::COBBLE),
+      _ => None,
+    });
+    assert_eq!(grass(&covered, (6, 10, 5)), vec![]);
+  }
+
+  #[test]
+  fn covered_grass_decays() {
+    let world = world(|c| match c {
+      (5, 10, 5) => Some(BlockId::GRASS),
+      (5, 11, 5) => Some(BlockId::COBBLE),
+      _ => None,
+    });
+    assert_eq!(grass(&world, (6, 10, 5)), vec![((5, 10, 5), BlockId::DIRT)]);
+  }
+
+  #[test]
+  fn nothing_grows_without_age() {
+    let world = world(|_| None);
+    let registry = BlockRegistry::default();
+    let grow = TickBehaviour::Grow { chance: 1.0 };
+    let outcome = grow.tick(Block::new(BlockId::DIRT), (5, 10, 5), &world, &registry, || 0.0);
+    assert_eq!(changes(&outcome), vec![]);
+  }
+
+  #[test]
+  fn sand_falls_onto_the_ground() {
+    let registry = BlockRegistry::default();
+    let sand = registry.id("sand").unwrap();
+    let torch = registry.id("torch").unwrap();
+    let world = world(|c| match c {
+      (5, 14, 5) | (5, 11, 5) | (7, 0, 7) => Some(sand),
+      (6, 11, 6) => Some(torch),
+      (7, 1..=10, 7) => Some(BlockId::AIR),
+      _ => None,
+    });
+    assert!(unsupported((5, 14, 5), &world, &registry));
+    assert!(unsupported((6, 12, 6), &world, &registry));
+    assert!(!unsupported((5, 11, 5), &world, &registry));
+    // The bottom of the world holds it up, so do chunks that aren't loaded
+    assert!(!unsupported((7, 0, 7), &world, &registry));
+    assert!(!unsupported((20, 11, 5), &world, &registry));
+
+    let outcome = tick(&world, TickKind::Scheduled, sand, (5, 14, 5));
+    assert_eq!(changes(&outcome), vec![((5, 14, 5), BlockId::AIR), ((5, 13, 5), sand)]);
+    // Random ticks leave it hanging, only a block update schedules the fall
+    let outcome = tick(&world, TickKind::Random, sand, (5, 14, 5));
+    assert_eq!(changes(&outcome), vec![]);
+    let outcome = neighbour_changed(&world, sand, (5, 14, 5), (5, 13, 5));
+    assert_eq!(outcome.schedule, vec![((5, 14, 5), FALL_DELAY)]);
+    let outcome = neighbour_changed(&world, sand, (5, 11, 5), (5, 10, 5));
+    assert!(outcome.changes.is_empty() && outcome.schedule.is_empty());
+  }
+
+  #[test]
+  fn sand_pops_the_torch_it_falls_into() {
+    let registry = BlockRegistry::default();
+    let sand = registry.id("sand").unwrap();
+    let torch = registry.id("torch").unwrap();
+    let world = world(|c| match c {
+      (5, 12, 5) => Some(sand),
+      (5, 11, 5) => Some(torch),
+      _ => None,
+    });
+    let outcome = tick(&world, TickKind::Scheduled, sand, (5, 12, 5));
+    assert_eq!(changes(&outcome), vec![((5, 12, 5), BlockId::AIR), ((5, 11, 5), sand)]);
+    assert_eq!(outcome.popped, vec![(5, 11, 5)]);
+    // Falling through air pops nothing
+    let outcome = tick(&world, TickKind::Scheduled, sand, (5, 14, 5));
+    assert_eq!(outcome.popped, vec![]);
+  }
+
+  #[test]
+  fn torches_pop_off_without_support() {
+    let registry = BlockRegistry::default();
+    let torch = registry.id("torch").unwrap();
+    let world = world(|c| (c == (5, 12, 5)).then_some(torch));
+    let outcome = neighbour_changed(&world, torch, (5, 12, 5), (5, 11, 5));
+    assert_eq!(changes(&outcome), vec![((5, 12, 5), BlockId::AIR)]);
+    assert_eq!(outcome.popped, vec![(5, 12, 5)]);
+    let outcome = neighbour_changed(&world, torch, (5, 11, 5), (5, 10, 5));
+    assert_eq!(changes(&outcome), vec![]);
+    // Ticks scheduled to try again once nobody could take the torch pop it off the same way
+    let outcome = tick(&world, TickKind::Scheduled, torch, (5, 12, 5));
+    assert_eq!(changes(&outcome), vec![((5, 12, 5), BlockId::AIR)]);
+    assert_eq!(outcome.popped, vec![(5, 12, 5)]);
+    let outcome = tick(&world, TickKind::Random, torch, (5, 12, 5));
+    assert_eq!(changes(&outcome), vec![]);
+  }
+}
@@ -1,84 +1,328 @@
 use bevy::prelude::*;
-use noise::{NoiseFn, Perlin};
-use rand::prelude::*;
 
 use crate::ecs::components::blocks::block_id::BlockId;
-use crate::ecs::components::blocks::Block;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::blocks::{Block, BlockMeta};
 use crate::ecs::resources::light::LightLevel;
-use crate::util::array::{Array, Array2d, Array3d, Bounds, DD, DDD};
+use crate::util::array::{Array, Array2d, Array3d, ArrayIndex, Bounds, ImmediateNeighbours, DD, DDD};
+use crate::worldgen::biome::BiomeId;
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
 
-pub const CHUNK_MAX_HEIGHT: i32 = 127;
+pub const SECTION_HEIGHT: i32 = 16;
+pub const DEFAULT_WORLD_HEIGHT: i32 = 256;
 
-#[derive(Component, Serialize, Deserialize)]
-pub struct Chunk {
+// What every block of a missing section is
+static EMPTY_BLOCK: Block = Block {
+  block: BlockId::AIR,
+  meta: BlockMeta { v: 0 },
+  entity: Entity::from_bits(0),
+};
+const EMPTY_LIGHT: LightLevel = LightLevel {
+  heaven: 16,
+  hearth: 0,
+  light_source: 0,
+};
+
+#[derive(Serialize, Deserialize)]
+pub struct Section {
   pub grid: Array3d<Block>,
   pub light_map: Array3d<LightLevel>,
 }
 
-fn noise(perlin: &Perlin, c: DDD) -> f64 {
-  perlin.get([c.0 as f64 / 20.0, 0.0, c.2 as f64 / 20.0])
+impl Section {
+  pub fn new(bounds: Bounds<DDD>) -> Self {
+    Self {
+      grid: Array::new_init(bounds, |_| EMPTY_BLOCK),
+      light_map: Array::new_init(bounds, |_| EMPTY_LIGHT),
+    }
+  }
+
+  // Air under open sky, no different from a missing section
+  pub fn is_empty(&self) -> bool {
+    let mut empty = true;
+    self.grid.foreach(|c, block| {
+      let light_level = self.light_map[c];
+      empty &= block.block == BlockId::AIR
+        && block.entity == Entity::from_bits(0)
+        && light_level.heaven == EMPTY_LIGHT.heaven
+        && light_level.hearth == 0
+        && light_level.light_source == 0;
+    });
+    empty
+  }
+
+  pub fn clear_entities(&mut self) {
+    self.grid.map_in_place(|_, block| Block {
+      entity: Entity::from_bits(0),
+      ..*block
+    });
+  }
+}
+
+#[derive(Component, Serialize, Deserialize)]
+pub struct Chunk {
+  pub coord: DD,
+  // Bottom to top, missing sections are all air with full sunlight
+  pub sections: Vec<Option<Section>>,
+  pub biomes: Array2d<BiomeId>,
 }
 
 impl Chunk {
-  pub fn new<F: Fn(DDD) -> BlockId>(bounds: Bounds<DDD>, block_f: F) -> Self {
+  pub fn new<F: Fn(DDD) -> BlockId>(chunk_coord: DD, height: i32, registry: &BlockRegistry, block_f: F) -> Self {
+    Self::with_biomes(
+      chunk_coord,
+      height,
+      Array::new_init(
+        (
+          (chunk_coord.0 * 16, chunk_coord.1 * 16),
+          (chunk_coord.0 * 16 + 15, chunk_coord.1 * 16 + 15),
+        ),
+        |_| BiomeId::default(),
+      ),
+      registry,
+      block_f,
+    )
+  }
+
+  pub fn with_biomes<F: Fn(DDD) -> BlockId>(
+    chunk_coord: DD,
+    height: i32,
+    biomes: Array2d<BiomeId>,
+    registry: &BlockRegistry,
+    block_f: F,
+  ) -> Self {
+    let sections = (0..(height + SECTION_HEIGHT - 1) / SECTION_HEIGHT)
+      .map(|index| {
+        let grid = Array::new_init(section_bounds(chunk_coord, index as usize), |c| Block::new(block_f(c)));
+        let mut empty = true;
+        grid.foreach(|_, block| empty &= block.block == BlockId::AIR);
+        if empty {
+          None
+        } else {
+          let light_map = Array::new_init(grid.bounds, |_| EMPTY_LIGHT);
+          Some(Section { grid, light_map })
+        }
+      })
+      .collect();
     let mut chunk = Self {
-      grid: Array::new_init(bounds, |c| Block::new(block_f(c))),
-      light_map: Array::new_init(bounds, |_| LightLevel::new(0, 0, 0)),
+      coord: chunk_coord,
+      sections,
+      biomes,
     };
+    chunk.relight_sky(registry);
+    chunk
+  }
+
+  // Same chunk without any of the sections, sections get sent to clients on their own
+  pub fn skeleton(&self) -> Self {
+    Self {
+      coord: self.coord,
+      sections: self.sections.iter().map(|_| None).collect(),
+      biomes: self.biomes.clone(),
+    }
+  }
+
+  pub fn height(&self) -> i32 {
+    self.sections.len() as i32 * SECTION_HEIGHT
+  }
+
+  pub fn bounds(&self) -> Bounds<DDD> {
+    (
+      (self.coord.0 * 16, 0, self.coord.1 * 16),
+      (self.coord.0 * 16 + 15, self.height() - 1, self.coord.1 * 16 + 15),
+    )
+  }
+
+  pub fn section_index(y: i32) -> usize {
+    (y / SECTION_HEIGHT) as usize
+  }
+
+  pub fn get(&self, c: DDD) -> Option<&Block> {
+    if !c.in_bounds(&self.bounds()) {
+      return None;
+    }
+    match &self.sections[Self::section_index(c.1)] {
+      Some(section) => Some(&section.grid[c]),
+      None => Some(&EMPTY_BLOCK),
+    }
+  }
+
+  // Materializes the section of the block if it was missing
+  pub fn get_mut(&mut self, c: DDD) -> Option<&mut Block> {
+    if !c.in_bounds(&self.bounds()) {
+      return None;
+    }
+    Some(&mut self.section_mut(Self::section_index(c.1)).grid[c])
+  }
+
+  pub fn get_light_level(&self, c: DDD) -> Option<LightLevel> {
+    if !c.in_bounds(&self.bounds()) {
+      return None;
+    }
+    match &self.sections[Self::section_index(c.1)] {
+      Some(section) => Some(section.light_map[c]),
+      None => Some(EMPTY_LIGHT),
+    }
+  }
+
+  pub fn set_light_level(&mut self, c: DDD, light_level: LightLevel) -> Option<()> {
+    if !c.in_bounds(&self.bounds()) {
+      return None;
+    }
+    let index = Self::section_index(c.1);
+    // Full sunlight is what a missing section has anyway, no need to allocate it
+    if self.sections[index].is_none()
+      && light_level.heaven == EMPTY_LIGHT.heaven
+      && light_level.hearth == 0
+      && light_level.light_source == 0
+    {
+      return Some(());
+    }
+    self.section_mut(index).light_map[c] = light_level;
+    Some(())
+  }
+
+  pub fn section_mut(&mut self, index: usize) -> &mut Section {
+    let chunk_coord = self.coord;
+    self.sections[index].get_or_insert_with(|| Section::new(section_bounds(chunk_coord, index)))
+  }
+
+  // Goes over the blocks of present sections only, everything else is air
+  pub fn foreach<F: FnMut(DDD, &Block)>(&self, mut f: F) {
+    for section in self.sections.iter().flatten() {
+      section.grid.foreach(&mut f);
+    }
+  }
+
+  pub fn clear_entities(&mut self) {
+    for section in self.sections.iter_mut().flatten() {
+      section.clear_entities();
+    }
+  }
+
+  // Drops the sections that went back to being air under open sky
+  pub fn compact(&mut self) {
+    for section in self.sections.iter_mut() {
+      if section.as_ref().is_some_and(|section| section.is_empty()) {
+        *section = None;
+      }
+    }
+  }
+
+  // Recomputes sunlight from scratch, the light of other sources is left as it is
+  pub fn relight_sky(&mut self, registry: &BlockRegistry) {
+    let bounds = self.bounds();
+    // Everything above the topmost section is lit by the sky and stays missing
+    let top = match self.sections.iter().rposition(|section| section.is_some()) {
+      None => return,
+      Some(top) => top,
+    };
+    for index in 0..=top {
+      self
+        .section_mut(index)
+        .light_map
+        .map_in_place(|_, light_level| LightLevel {
+          heaven: 0,
+          ..*light_level
+        });
+    }
+    let mut queue = VecDeque::new();
     for ix in bounds.0 .0..=bounds.1 .0 {
       for iz in bounds.0 .2..=bounds.1 .2 {
-        for iy in (0..=CHUNK_MAX_HEIGHT).rev() {
-          if chunk.grid[(ix, iy, iz)].visible() {
+        for iy in (0..(top as i32 + 1) * SECTION_HEIGHT).rev() {
+          let section = self.sections[Self::section_index(iy)].as_mut().unwrap();
+          if registry.get(section.grid[(ix, iy, iz)].block).opaque() {
             break;
           }
-          chunk.light_map[(ix, iy, iz)] = LightLevel::new(16, 0, 0);
+          section.light_map[(ix, iy, iz)].heaven = 16;
+          queue.push_back((ix, iy, iz));
         }
       }
     }
-    chunk
+    // Sunlight falls sideways into cave mouths and under overhangs, fading one level per block like relighting does.
+    while let Some(c) = queue.pop_front() {
+      let heaven = self.get_light_level(c).unwrap().heaven.saturating_sub(1);
+      for neighbour in c.immediate_neighbours() {
+        if neighbour.in_bounds(&bounds)
+          && !registry.get(self.get(neighbour).unwrap().block).opaque()
+          && self.get_light_level(neighbour).unwrap().heaven < heaven
+        {
+          let index = Self::section_index(neighbour.1);
+          self.section_mut(index).light_map[neighbour].heaven = heaven;
+          queue.push_back(neighbour);
+        }
+      }
+    }
+    self.compact();
   }
+}
 
-  pub async fn generate(coord: DD) -> Chunk {
-    let perlin = Perlin::new(11);
-    let from = (coord.0 * 16, 0, coord.1 * 16);
-    let to = (coord.0 * 16 + 15, CHUNK_MAX_HEIGHT, coord.1 * 16 + 15);
-    let perlin_top = Perlin::new(12);
-    let v = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |(x, z)| noise(&perlin, (x, 0, z)));
-    let vtop = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |(x, z)| noise(&perlin_top, (x, 0, z)));
-
-    Chunk::new((from, to), |(x, y, z)| {
-      let bottom = v[(x, z)];
-      let top = vtop[(x, z)];
-
-      let bottom_extent = (bottom * 30.0).floor() as i32;
-      let top_extent = ((top + 1.0) * bottom / 2.0 * 30.0).floor() as i32;
-
-      if bottom <= 0.0 {
-        BlockId::Air
-      } else if y < 30 {
-        if (30 - y) < bottom_extent {
-          if rand::thread_rng().gen_range(0..10) == 0 {
-            BlockId::Iron
-          } else {
-            BlockId::Cobble
-          }
-        } else {
-          BlockId::Air
-        }
-      } else if (y - 30) > top_extent {
-        BlockId::Air
-      } else if (y - 30) == top_extent {
-        BlockId::Grass
-      } else if y - 28 >= top_extent {
-        BlockId::Dirt
+pub fn section_bounds(chunk_coord: DD, index: usize) -> Bounds<DDD> {
+  let y = index as i32 * SECTION_HEIGHT;
+  (
+    (chunk_coord.0 * 16, y, chunk_coord.1 * 16),
+    (chunk_coord.0 * 16 + 15, y + SECTION_HEIGHT - 1, chunk_coord.1 * 16 + 15),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Cobble below `ground` and whatever `cobble` adds, air everywhere else
+  fn chunk(ground: i32, cobble: impl Fn(DDD) -> bool) -> Chunk {
+    Chunk::new((1, -2), DEFAULT_WORLD_HEIGHT, &BlockRegistry::default(), |c| {
+      if c.1 < ground || cobble(c) {
+        BlockId::COBBLE
       } else {
-        if rand::thread_rng().gen_range(0..10) == 0 {
-          BlockId::Iron
-        } else {
-          BlockId::Cobble
-        }
+        BlockId::AIR
       }
     })
   }
+
+  #[test]
+  fn air_sections_are_not_stored() {
+    let chunk = chunk(20, |_| false);
+    assert_eq!(chunk.sections.len(), (DEFAULT_WORLD_HEIGHT / SECTION_HEIGHT) as usize);
+    assert!(chunk.sections[..2].iter().all(Option::is_some));
+    assert!(chunk.sections[2..].iter().all(Option::is_none));
+    assert_eq!(chunk.get((20, 200, -20)).unwrap().block, BlockId::AIR);
+    assert_eq!(chunk.get_light_level((20, 200, -20)).unwrap().heaven, 16);
+    assert!(chunk.get((20, DEFAULT_WORLD_HEIGHT, -20)).is_none());
+    assert!(chunk.get((0, 10, -20)).is_none());
+  }
+
+  #[test]
+  fn sections_back_to_air_are_dropped() {
+    let registry = BlockRegistry::default();
+    let mut chunk = chunk(20, |_| false);
+    chunk.section_mut(10);
+    chunk.compact();
+    assert!(chunk.sections[10].is_none());
+
+    *chunk.get_mut((20, 100, -20)).unwrap() = Block::new(BlockId::COBBLE);
+    chunk.relight_sky(&registry);
+    // The shade under the block keeps the sections below it around as well
+    assert!(chunk.sections[2..=6].iter().all(Option::is_some));
+    assert!(chunk.sections[7..].iter().all(Option::is_none));
+    assert_eq!(chunk.get_light_level((20, 99, -20)).unwrap().heaven, 15);
+
+    *chunk.get_mut((20, 100, -20)).unwrap() = Block::new(BlockId::AIR);
+    chunk.relight_sky(&registry);
+    assert!(chunk.sections[2..].iter().all(Option::is_none));
+  }
+
+  #[test]
+  fn sunlight_fades_under_an_overhang() {
+    // A slab at y 40 over the x 16 to 23 half of the chunk, open to the sky past x 23
+    let chunk = chunk(20, |(x, y, _)| y == 40 && x < 24);
+    assert_eq!(chunk.get_light_level((20, 41, -25)).unwrap().heaven, 16);
+    assert_eq!(chunk.get_light_level((24, 30, -25)).unwrap().heaven, 16);
+    assert_eq!(chunk.get_light_level((23, 30, -25)).unwrap().heaven, 15);
+    assert_eq!(chunk.get_light_level((20, 30, -25)).unwrap().heaven, 12);
+    // Nothing comes in past the edge of the chunk
+    assert_eq!(chunk.get_light_level((16, 30, -25)).unwrap().heaven, 8);
+    assert_eq!(chunk.get_light_level((20, 10, -25)).unwrap().heaven, 0);
+  }
 }
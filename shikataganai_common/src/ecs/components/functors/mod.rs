@@ -1,9 +1,10 @@
-use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
 use crate::ecs::components::blocks::{BlockOrItem, QuantifiedBlockOrItem};
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Component, Default, Serialize, Deserialize)]
+#[derive(Component, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub struct InternalInventory {
   pub inventory: Vec<Option<QuantifiedBlockOrItem>>,
 }
@@ -11,21 +12,200 @@ pub struct InternalInventory {
 impl InternalInventory {
   pub fn with_capacity(len: usize) -> Self {
     Self {
-      inventory: (0..len)
-        .map(|x| {
-          if x == 2 {
-            Some(QuantifiedBlockOrItem {
-              block_or_item: BlockOrItem::Block(BlockId::Dirt),
-              quant: 5,
-            })
-          } else {
-            Some(QuantifiedBlockOrItem {
-              block_or_item: BlockOrItem::Block(BlockId::Cobble),
-              quant: 5,
+      inventory: vec![None; len],
+    }
+  }
+
+  // Stacks onto the first slot holding the same thing or else into the first empty one, None when there's no room
+  pub fn add(&mut self, block_or_item: BlockOrItem, quant: u32) -> Option<usize> {
+    let slot = self
+      .inventory
+      .iter()
+      .position(|slot| slot.as_ref().is_some_and(|stack| stack.block_or_item == block_or_item))
+      .or_else(|| self.inventory.iter().position(|slot| slot.is_none()))?;
+    self.inventory[slot]
+      .get_or_insert(QuantifiedBlockOrItem {
+        block_or_item,
+        quant: 0,
+      })
+      .quant += quant;
+    Some(slot)
+  }
+
+  // Whether `add` would find room for all of these
+  pub fn fits(&self, stacks: &[QuantifiedBlockOrItem]) -> bool {
+    let mut trial = self.clone();
+    stacks
+      .iter()
+      .all(|stack| trial.add(stack.block_or_item, stack.quant).is_some())
+  }
+
+  // Takes one out of the slot if that's what it holds
+  pub fn take_one(&mut self, slot: usize, block_or_item: BlockOrItem) -> bool {
+    match self.inventory.get_mut(slot) {
+      Some(stack) if stack.as_ref().is_some_and(|stack| stack.block_or_item == block_or_item) => {
+        let quant = &mut stack.as_mut().unwrap().quant;
+        *quant -= 1;
+        if *quant == 0 {
+          *stack = None;
+        }
+        true
+      }
+      _ => false,
+    }
+  }
+
+  pub fn slots(&self, slots: impl IntoIterator<Item = usize>) -> Vec<(usize, Option<QuantifiedBlockOrItem>)> {
+    slots
+      .into_iter()
+      .map(|slot| (slot, self.inventory.get(slot).cloned().flatten()))
+      .collect()
+  }
+}
+
+// Fuel burning and the input slot cooking, the slots themselves are the InternalInventory of the same entity
+#[derive(Component, Default, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Furnace {
+  // Server ticks the fuel that's burning keeps going for, out of the ones it gave in total
+  pub burn: u32,
+  pub burn_total: u32,
+  // Server ticks the input has been cooking for, done at COOK_TIME
+  pub progress: u32,
+}
+
+impl Furnace {
+  pub const INPUT: usize = 0;
+  pub const FUEL: usize = 1;
+  pub const OUTPUT: usize = 2;
+  pub const COOK_TIME: u32 = 200;
+
+  // Nothing can be put into the output, it only gets taken from
+  pub fn accepts(slot: usize, block_or_item: BlockOrItem, registry: &BlockRegistry) -> bool {
+    match slot {
+      Furnace::INPUT => block_or_item.smelt(registry).is_some(),
+      Furnace::FUEL => block_or_item.burn_time().is_some(),
+      _ => false,
+    }
+  }
+
+  // One server tick, the fuel burns down whether there's anything to cook or not
+  pub fn tick(&mut self, slots: &mut InternalInventory, registry: &BlockRegistry) {
+    let input = slots.inventory.get(Furnace::INPUT).cloned().flatten();
+    let output = slots.inventory.get(Furnace::OUTPUT).cloned();
+    // Cooking needs room for the result in the output slot
+    let result = input
+      .as_ref()
+      .and_then(|input| input.block_or_item.smelt(registry))
+      .filter(|result| match &output {
+        Some(None) => true,
+        Some(Some(output)) => output.block_or_item == result.block_or_item,
+        None => false,
+      });
+    if self.burn > 0 {
+      self.burn -= 1;
+    }
+    if self.burn == 0 && result.is_some() {
+      let fuel = slots.inventory.get(Furnace::FUEL).cloned().flatten();
+      if let Some((fuel, burn_time)) = fuel.and_then(|fuel| fuel.block_or_item.burn_time().map(|time| (fuel, time))) {
+        slots.take_one(Furnace::FUEL, fuel.block_or_item);
+        self.burn = burn_time;
+        self.burn_total = burn_time;
+      }
+    }
+    match (result, input) {
+      (Some(result), Some(input)) if self.burn > 0 => {
+        self.progress += 1;
+        if self.progress >= Furnace::COOK_TIME {
+          self.progress = 0;
+          slots.take_one(Furnace::INPUT, input.block_or_item);
+          slots.inventory[Furnace::OUTPUT]
+            .get_or_insert(QuantifiedBlockOrItem {
+              block_or_item: result.block_or_item,
+              quant: 0,
             })
-          }
-        })
-        .collect(),
+            .quant += result.quant;
+        }
+      }
+      _ => self.progress = 0,
+    }
+  }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum SlotTransaction {
+  Move,
+  Split(u32),
+  Merge,
+}
+
+impl SlotTransaction {
+  // Works on what two different slots hold, returns false and leaves them as they were if it doesn't add up
+  pub fn apply(self, from: &mut Option<QuantifiedBlockOrItem>, to: &mut Option<QuantifiedBlockOrItem>) -> bool {
+    let (block_or_item, available) = match from {
+      None => return false,
+      Some(stack) => (stack.block_or_item, stack.quant),
+    };
+    let quant = match self {
+      SlotTransaction::Move => {
+        std::mem::swap(from, to);
+        return true;
+      }
+      SlotTransaction::Split(quant) => quant,
+      SlotTransaction::Merge => available,
+    };
+    if quant == 0 || quant > available {
+      return false;
+    }
+    match to {
+      None => *to = Some(QuantifiedBlockOrItem { block_or_item, quant }),
+      Some(stack) if stack.block_or_item == block_or_item => stack.quant += quant,
+      Some(_) => return false,
+    }
+    match from {
+      Some(stack) if stack.quant > quant => stack.quant -= quant,
+      _ => *from = None,
+    }
+    true
+  }
+}
+
+// Functor components of a block entity as they are stored on disk together with the chunk.
+#[derive(Serialize, Deserialize)]
+pub enum SavedFunctor {
+  InternalInventory(InternalInventory),
+  Furnace(Furnace),
+}
+
+impl SavedFunctor {
+  pub fn insert(self, commands: &mut EntityCommands) {
+    match self {
+      SavedFunctor::InternalInventory(functor) => {
+        commands.insert(functor);
+      }
+      SavedFunctor::Furnace(functor) => {
+        commands.insert(functor);
+      }
+    }
+  }
+}
+
+// Functors a block's definition gives its entity when the block gets placed
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FunctorDef {
+  InternalInventory { capacity: usize },
+  Furnace,
+}
+
+impl FunctorDef {
+  pub fn insert(&self, commands: &mut EntityCommands) {
+    match self {
+      FunctorDef::InternalInventory { capacity } => {
+        commands.insert(InternalInventory::with_capacity(*capacity));
+      }
+      FunctorDef::Furnace => {
+        commands.insert(Furnace::default());
+      }
     }
   }
 }
@@ -33,3 +213,182 @@ impl InternalInventory {
 pub enum FunctorTransit {
   InternalInventory(Vec<QuantifiedBlockOrItem>),
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::ecs::components::blocks::block_id::BlockId;
+  use crate::ecs::components::item::ItemId;
+
+  fn stack(block_or_item: BlockOrItem, quant: u32) -> Option<QuantifiedBlockOrItem> {
+    Some(QuantifiedBlockOrItem { block_or_item, quant })
+  }
+
+  const DIRT: BlockOrItem = BlockOrItem::Block(BlockId::DIRT);
+  const COAL: BlockOrItem = BlockOrItem::Item(ItemId::Coal);
+  const IRON: BlockOrItem = BlockOrItem::Item(ItemId::Iron);
+
+  #[test]
+  fn move_swaps_slots() {
+    let (mut from, mut to) = (stack(DIRT, 5), stack(COAL, 2));
+    assert!(SlotTransaction::Move.apply(&mut from, &mut to));
+    assert_eq!((from, to), (stack(COAL, 2), stack(DIRT, 5)));
+    let (mut from, mut to) = (stack(DIRT, 5), None);
+    assert!(SlotTransaction::Move.apply(&mut from, &mut to));
+    assert_eq!((from, to), (None, stack(DIRT, 5)));
+  }
+
+  #[test]
+  fn split_takes_part_of_the_stack() {
+    let (mut from, mut to) = (stack(DIRT, 5), None);
+    assert!(SlotTransaction::Split(2).apply(&mut from, &mut to));
+    assert_eq!((from.clone(), to.clone()), (stack(DIRT, 3), stack(DIRT, 2)));
+    assert!(SlotTransaction::Split(3).apply(&mut from, &mut to));
+    assert_eq!((from, to), (None, stack(DIRT, 5)));
+  }
+
+  #[test]
+  fn merge_stacks_onto_the_same_kind() {
+    let (mut from, mut to) = (stack(COAL, 4), stack(COAL, 3));
+    assert!(SlotTransaction::Merge.apply(&mut from, &mut to));
+    assert_eq!((from, to), (None, stack(COAL, 7)));
+  }
+
+  #[test]
+  fn failed_transactions_leave_the_slots_alone() {
+    let failing = [
+      (SlotTransaction::Split(0), stack(DIRT, 5), None),
+      (SlotTransaction::Split(6), stack(DIRT, 5), None),
+      (SlotTransaction::Split(1), stack(DIRT, 5), stack(COAL, 1)),
+      (SlotTransaction::Merge, stack(DIRT, 5), stack(COAL, 1)),
+      (SlotTransaction::Merge, None, stack(COAL, 1)),
+      (SlotTransaction::Move, None, stack(COAL, 1)),
+    ];
+    for (transaction, from, to) in failing {
+      let (mut from_after, mut to_after) = (from.clone(), to.clone());
+      assert!(!transaction.apply(&mut from_after, &mut to_after));
+      assert_eq!((from_after, to_after), (from, to));
+    }
+  }
+
+  #[test]
+  fn add_stacks_before_taking_an_empty_slot() {
+    let mut inventory = InternalInventory {
+      inventory: vec![None; 3],
+    };
+    inventory.inventory[1] = stack(COAL, 1);
+    assert_eq!(inventory.add(DIRT, 2), Some(0));
+    assert_eq!(inventory.add(COAL, 3), Some(1));
+    assert_eq!(inventory.add(DIRT, 1), Some(0));
+    assert_eq!(inventory.inventory, vec![stack(DIRT, 3), stack(COAL, 4), None]);
+  }
+
+  #[test]
+  fn add_fails_when_full() {
+    let mut inventory = InternalInventory {
+      inventory: vec![None; 1],
+    };
+    assert_eq!(inventory.add(COAL, 1), Some(0));
+    assert_eq!(inventory.add(DIRT, 1), None);
+    assert_eq!(inventory.inventory, vec![stack(COAL, 1)]);
+  }
+
+  #[test]
+  fn fits_needs_room_for_everything() {
+    let mut inventory = InternalInventory {
+      inventory: vec![None; 2],
+    };
+    inventory.inventory[0] = stack(COAL, 1);
+    let coal_and_dirt = [stack(COAL, 5).unwrap(), stack(DIRT, 1).unwrap()];
+    assert!(inventory.fits(&coal_and_dirt));
+    assert!(inventory.fits(&[stack(DIRT, 1).unwrap(), stack(DIRT, 2).unwrap()]));
+    assert!(!inventory.fits(&[stack(DIRT, 1).unwrap(), stack(IRON, 1).unwrap()]));
+    // Checking leaves the inventory as it was
+    assert_eq!(inventory.inventory, vec![stack(COAL, 1), None]);
+  }
+
+  #[test]
+  fn take_one_only_takes_what_the_slot_holds() {
+    let mut inventory = InternalInventory {
+      inventory: vec![None; 2],
+    };
+    inventory.inventory[0] = stack(DIRT, 2);
+    assert!(!inventory.take_one(0, COAL));
+    assert!(!inventory.take_one(1, DIRT));
+    assert!(!inventory.take_one(5, DIRT));
+    assert!(inventory.take_one(0, DIRT));
+    assert_eq!(inventory.inventory[0], stack(DIRT, 1));
+    assert!(inventory.take_one(0, DIRT));
+    assert_eq!(inventory.inventory[0], None);
+    assert!(!inventory.take_one(0, DIRT));
+  }
+
+  fn furnace(input: Option<QuantifiedBlockOrItem>, fuel: Option<QuantifiedBlockOrItem>) -> InternalInventory {
+    InternalInventory {
+      inventory: vec![input, fuel, None],
+    }
+  }
+
+  #[test]
+  fn furnace_smelts_with_fuel() {
+    let registry = BlockRegistry::default();
+    let sand = BlockOrItem::Block(registry.id("sand").unwrap());
+    let glass = BlockOrItem::Block(registry.id("glass").unwrap());
+    let mut slots = furnace(stack(sand, 2), stack(COAL, 1));
+    let mut state = Furnace::default();
+    state.tick(&mut slots, &registry);
+    // The coal is lit as soon as there's something to cook
+    assert_eq!(slots.inventory[Furnace::FUEL], None);
+    assert_eq!((state.burn, state.burn_total, state.progress), (1600, 1600, 1));
+    for _ in 1..Furnace::COOK_TIME {
+      state.tick(&mut slots, &registry);
+    }
+    assert_eq!(slots.inventory, vec![stack(sand, 1), None, stack(glass, 1)]);
+    assert_eq!(state.progress, 0);
+    for _ in 0..Furnace::COOK_TIME {
+      state.tick(&mut slots, &registry);
+    }
+    assert_eq!(slots.inventory, vec![None, None, stack(glass, 2)]);
+    // The tick that lit the coal doesn't burn any of it yet
+    assert_eq!(state.burn, 1600 - (2 * Furnace::COOK_TIME - 1));
+  }
+
+  #[test]
+  fn furnace_keeps_its_fuel_without_anything_to_cook() {
+    let registry = BlockRegistry::default();
+    let mut slots = furnace(stack(DIRT, 1), stack(COAL, 1));
+    let mut state = Furnace::default();
+    state.tick(&mut slots, &registry);
+    assert_eq!(state, Furnace::default());
+    assert_eq!(slots.inventory, vec![stack(DIRT, 1), stack(COAL, 1), None]);
+  }
+
+  #[test]
+  fn furnace_waits_for_room_in_the_output() {
+    let registry = BlockRegistry::default();
+    let iron = BlockOrItem::Block(BlockId::IRON);
+    let mut slots = furnace(stack(iron, 1), stack(COAL, 1));
+    slots.inventory[Furnace::OUTPUT] = stack(DIRT, 1);
+    let mut state = Furnace::default();
+    state.tick(&mut slots, &registry);
+    assert_eq!(state, Furnace::default());
+    assert_eq!(slots.inventory[Furnace::FUEL], stack(COAL, 1));
+  }
+
+  #[test]
+  fn furnace_loses_progress_when_the_fuel_runs_out() {
+    let registry = BlockRegistry::default();
+    let iron = BlockOrItem::Block(BlockId::IRON);
+    let mut slots = furnace(stack(iron, 1), None);
+    let mut state = Furnace {
+      burn: 2,
+      burn_total: 1600,
+      progress: 100,
+    };
+    state.tick(&mut slots, &registry);
+    assert_eq!((state.burn, state.progress), (1, 101));
+    state.tick(&mut slots, &registry);
+    assert_eq!((state.burn, state.progress), (0, 0));
+    assert_eq!(slots.inventory[Furnace::INPUT], stack(iron, 1));
+  }
+}
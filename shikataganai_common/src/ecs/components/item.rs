@@ -1,8 +1,35 @@
 use serde::{Deserialize, Serialize};
 
+// Block definitions name items in lowercase, the save formats only use the variant index
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ItemId {
   Coal,
   Wand,
   Iron,
+  Pickaxe,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolKind {
+  Pickaxe,
+}
+
+impl ItemId {
+  // Kind of the tool and how many times faster it breaks the blocks that prefer it
+  pub fn tool(&self) -> Option<(ToolKind, f32)> {
+    match self {
+      ItemId::Pickaxe => Some((ToolKind::Pickaxe, 4.0)),
+      _ => None,
+    }
+  }
+
+  // Server ticks it keeps a furnace burning for
+  pub fn burn_time(&self) -> Option<u32> {
+    match self {
+      ItemId::Coal => Some(1600),
+      _ => None,
+    }
+  }
 }
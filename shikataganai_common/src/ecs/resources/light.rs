@@ -1,3 +1,4 @@
+use crate::ecs::components::blocks::registry::BlockRegistry;
 use crate::ecs::resources::world::GameWorld;
 use crate::util::array::{ImmediateNeighbours, DDD};
 use bevy::prelude::*;
@@ -50,9 +51,15 @@ impl LightLevel {
   }
 }
 
-pub fn do_relight(coord: DDD, game_world: &mut GameWorld, remesh: &mut HashSet<DDD>, queue: &mut VecDeque<DDD>) {
+pub fn do_relight(
+  coord: DDD,
+  game_world: &mut GameWorld,
+  registry: &BlockRegistry,
+  remesh: &mut HashSet<DDD>,
+  queue: &mut VecDeque<DDD>,
+) {
   if let Some(light_level) = game_world.get_light_level(coord) && let Some(block) = game_world.get(coord) {
-    if block.visible() {
+    if registry.get(block.block).opaque() {
       return;
     }
     let (heavens, hearths): (Vec<_>, Vec<_>) = coord
@@ -76,13 +83,14 @@ pub fn do_relight(coord: DDD, game_world: &mut GameWorld, remesh: &mut HashSet<D
         }
       ])
       .max().unwrap_or(&0).saturating_sub(1);
-    let max_hearth = hearths.iter().max().unwrap_or(&0).saturating_sub(1);
-    let changed = (light_level.light_source < max_hearth.max(light_level.hearth) && light_level.hearth != max_hearth) || (light_level.heaven != max_heaven);
+    // Like sunlight it can go down as well, so light fades out once its source is gone
+    let max_hearth = hearths.iter().max().unwrap_or(&0).saturating_sub(1).max(light_level.light_source);
+    let changed = light_level.hearth != max_hearth || light_level.heaven != max_heaven;
     if changed {
-      game_world.set_light_level(coord, LightLevel::new(max_heaven, light_level.light_source.max(light_level.hearth.max(max_hearth)), light_level.light_source));
+      game_world.set_light_level(coord, LightLevel::new(max_heaven, max_hearth, light_level.light_source));
       remesh.insert(coord);
       for neighbour in coord.immediate_neighbours() {
-        if !game_world.get(neighbour).map(|block|block.visible()).unwrap_or(true) {
+        if !game_world.get(neighbour).map(|block| registry.get(block.block).opaque()).unwrap_or(true) {
           queue.push_front(neighbour);
         }
       }
@@ -90,18 +98,30 @@ pub fn do_relight(coord: DDD, game_world: &mut GameWorld, remesh: &mut HashSet<D
   }
 }
 
-pub fn relight_helper(relight_events: &mut EventReader<RelightEvent>, game_world: &mut GameWorld) -> HashSet<DDD> {
+// Call when the block at `coord` changes, before sending a RelightEvent for it. Emitting blocks become a light source,
+// everything else starts dark and gets its light back from the neighbours when relit.
+pub fn seed_light(game_world: &mut GameWorld, registry: &BlockRegistry, coord: DDD) {
+  if let Some(emission) = game_world.get(coord).map(|block| registry.get(block.block).emission) {
+    game_world.set_light_level(coord, LightLevel::new(0, emission, emission));
+  }
+}
+
+pub fn relight_helper(
+  relight_events: &mut EventReader<RelightEvent>,
+  game_world: &mut GameWorld,
+  registry: &BlockRegistry,
+) -> HashSet<DDD> {
   let mut remesh = HashSet::new();
   for RelightEvent::Relight(coord) in relight_events.iter() {
     remesh.insert(*coord);
     let mut queue = VecDeque::new();
-    if game_world.get(*coord).map(|block| block.visible()).unwrap_or(false) {
+    if game_world.get(*coord).map(|block| registry.get(block.block).opaque()).unwrap_or(false) {
       coord.immediate_neighbours().for_each(|coord| queue.push_back(coord));
     } else {
       queue.push_back(*coord);
     }
     while let Some(coord) = queue.pop_front() {
-      do_relight(coord, game_world, &mut remesh, &mut queue);
+      do_relight(coord, game_world, registry, &mut remesh, &mut queue);
     }
   }
   remesh
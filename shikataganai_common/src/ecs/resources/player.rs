@@ -1,4 +1,9 @@
 use bevy::prelude::*;
 
+// Hot bar and the rest of the inventory
+pub const PLAYER_INVENTORY_SIZE: usize = 27;
+// Slots at the start of the inventory that can be held in hand
+pub const HOT_BAR_WIDTH: usize = 9;
+
 #[derive(Component, Clone, Resource)]
 pub struct PlayerNickname(pub String);
@@ -1,24 +1,50 @@
 use crate::ecs::components::blocks::Block;
-use crate::ecs::components::chunk::Chunk;
+use crate::ecs::components::chunk::{Chunk, SECTION_HEIGHT};
 use crate::ecs::resources::light::LightLevel;
-use crate::util::array::{ArrayIndex, DD, DDD};
+use crate::util::array::{DD, DDD};
 use bevy::ecs::system::Resource;
 use bevy::utils::hashbrown::HashMap;
 
+// Where a chunk is in its lifecycle. Clients only ever go through Requested and Loaded.
+#[derive(Debug)]
+pub enum ChunkState {
+  // Asked the server for the chunk, waiting for its data
+  Requested,
+  // Reading the chunk from its region file, clients in the list get it once it's there
+  Loading { waiters: Vec<u64> },
+  // Not on disk, generating it from scratch
+  Generating { waiters: Vec<u64> },
+  Loaded,
+  // Being written to disk before getting dropped, the chunk data is still accessible
+  Unloading,
+}
+
 #[derive(Default, Resource)]
 pub struct GameWorld {
-  // List of chunks that are being generated right now to avoid unnecessary matching on an enum for chunk access for 99.99% of runtime
-  pub generating: Vec<DD>,
+  // Chunk data is kept apart from the states to avoid unnecessary matching on an enum for chunk access for 99.99% of runtime
+  pub states: HashMap<DD, ChunkState>,
   pub chunks: HashMap<DD, Chunk>,
 }
 
 impl GameWorld {
-  pub fn remove_from_generating(&mut self, chunk_coord: DD) {
-    if let Some(index) = self.generating.iter().position(|x| *x == chunk_coord) {
-      self.generating.remove(index);
+  pub fn chunk_state(&self, chunk_coord: DD) -> Option<&ChunkState> {
+    self.states.get(&chunk_coord)
+  }
+
+  // Returns the waiters of the chunk, they still have to be sent the data
+  pub fn insert_chunk(&mut self, chunk_coord: DD, chunk: Chunk) -> Vec<u64> {
+    self.chunks.insert(chunk_coord, chunk);
+    match self.states.insert(chunk_coord, ChunkState::Loaded) {
+      Some(ChunkState::Loading { waiters }) | Some(ChunkState::Generating { waiters }) => waiters,
+      _ => vec![],
     }
   }
 
+  pub fn remove_chunk(&mut self, chunk_coord: DD) -> Option<Chunk> {
+    self.states.remove(&chunk_coord);
+    self.chunks.remove(&chunk_coord)
+  }
+
   pub fn get_chunk_coord(mut coord: DDD) -> DD {
     if coord.0 < 0 {
       coord.0 -= 15;
@@ -29,37 +55,32 @@ impl GameWorld {
     (coord.0 / 16, coord.2 / 16)
   }
 
+  // Chunk coordinates with the section index in the middle
+  pub fn get_section_coord(coord: DDD) -> DDD {
+    let chunk_coord = Self::get_chunk_coord(coord);
+    (chunk_coord.0, coord.1.div_euclid(SECTION_HEIGHT), chunk_coord.1)
+  }
+
   pub fn get(&self, c: DDD) -> Option<&Block> {
     let chunk_coord = Self::get_chunk_coord(c);
-    self.chunks.get(&chunk_coord).and_then(|chunk| {
-      if c.in_bounds(&chunk.grid.bounds) {
-        Some(&chunk.grid[c])
-      } else {
-        None
-      }
-    })
+    self.chunks.get(&chunk_coord).and_then(|chunk| chunk.get(c))
   }
 
   pub fn get_mut(&mut self, c: DDD) -> Option<&mut Block> {
     let chunk_coord = Self::get_chunk_coord(c);
-    self.chunks.get_mut(&chunk_coord).map(|chunk| &mut chunk.grid[c])
+    self.chunks.get_mut(&chunk_coord).and_then(|chunk| chunk.get_mut(c))
   }
 
   pub fn get_light_level(&self, c: DDD) -> Option<LightLevel> {
     let chunk_coord = Self::get_chunk_coord(c);
-    self.chunks.get(&chunk_coord).and_then(|chunk| {
-      if c.in_bounds(&chunk.light_map.bounds) {
-        Some(chunk.light_map[c])
-      } else {
-        None
-      }
-    })
+    self.chunks.get(&chunk_coord).and_then(|chunk| chunk.get_light_level(c))
   }
 
   pub fn set_light_level(&mut self, c: DDD, light_level: LightLevel) -> Option<()> {
     let chunk_coord = Self::get_chunk_coord(c);
-    self.chunks.get_mut(&chunk_coord).map(|chunk| {
-      chunk.light_map[c] = light_level;
-    })
+    self
+      .chunks
+      .get_mut(&chunk_coord)
+      .and_then(|chunk| chunk.set_light_level(c, light_level))
   }
 }
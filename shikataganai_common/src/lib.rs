@@ -3,3 +3,4 @@ pub mod ecs;
 pub mod networking;
 pub mod recipes;
 pub mod util;
+pub mod worldgen;
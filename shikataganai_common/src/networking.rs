@@ -1,7 +1,6 @@
 use crate::ecs::components::blocks::animation::Animation;
 use crate::ecs::components::blocks::block_id::BlockId;
-use crate::ecs::components::blocks::BlockMeta;
-use crate::ecs::components::item::ItemId;
+use crate::ecs::components::blocks::{BlockMeta, QuantifiedBlockOrItem};
 use crate::ecs::resources::light::LightLevel;
 use crate::util::array::{DD, DDD};
 use bevy::prelude::*;
@@ -68,6 +67,10 @@ type TranslationRotation = (Vec3, PolarRotation);
 
 #[derive(Debug, Serialize, Deserialize, Component)]
 pub enum ServerMessage {
+  // Block names indexed by the ids the server uses, sent before anything else
+  BlockRegistry {
+    names: Vec<String>,
+  },
   PlayerSpawn {
     entity: Entity,
     id: u64,
@@ -86,9 +89,15 @@ pub enum ServerMessage {
     location: DDD,
     block_transfer: BlockTransfer,
   },
+  // The chunk without its sections, the present ones follow as SectionData
   ChunkData {
     chunk: Vec<u8>,
   },
+  SectionData {
+    chunk_coord: DD,
+    section: u32,
+    data: Vec<u8>,
+  },
   Relight {
     relights: Vec<(DDD, LightLevel)>,
   },
@@ -102,25 +111,35 @@ pub enum ServerMessage {
     location: DDD,
     animation: Animation,
   },
-  ItemAdd {
-    item: ItemId,
-    quant: u32,
+  // Everything the player holds, sent once they're in, InventorySlots keep it up to date afterwards
+  PlayerInventory {
+    items: Vec<Option<QuantifiedBlockOrItem>>,
+  },
+  // What some slots hold after they've changed, or after a change to them got turned down
+  InventorySlots {
+    inventory: InventoryRef,
+    slots: Vec<(usize, Option<QuantifiedBlockOrItem>)>,
   },
+  ServerShutdown,
 }
 
 impl Display for ServerMessage {
   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
     match self {
+      ServerMessage::BlockRegistry { .. } => f.write_str("BlockRegistry"),
       ServerMessage::PlayerSpawn { .. } => f.write_str("PlayerSpawn"),
       ServerMessage::AuthConfirmed { .. } => f.write_str("AuthConfirmed"),
       ServerMessage::PlayerDespawn { .. } => f.write_str("PlayerDespawn"),
       ServerMessage::BlockRemove { .. } => f.write_str("BlockRemove"),
       ServerMessage::BlockPlace { .. } => f.write_str("BlockPlace"),
       ServerMessage::ChunkData { .. } => f.write_str("ChunkData"),
+      ServerMessage::SectionData { .. } => f.write_str("SectionData"),
       ServerMessage::Relight { .. } => f.write_str("Relight"),
       ServerMessage::Functor { .. } => f.write_str("Functor"),
       ServerMessage::AnimationStart { .. } => f.write_str("AnimationStart"),
-      ServerMessage::ItemAdd { .. } => f.write_str("ItemAdd"),
+      ServerMessage::PlayerInventory { .. } => f.write_str("PlayerInventory"),
+      ServerMessage::InventorySlots { .. } => f.write_str("InventorySlots"),
+      ServerMessage::ServerShutdown => f.write_str("ServerShutdown"),
     }
   }
 }
@@ -180,9 +199,10 @@ pub struct BlockTransfer {
   pub meta: BlockMeta,
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum FunctorType {
   InternalInventory,
+  Furnace,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -193,20 +213,35 @@ pub enum PlayerCommand {
   PlayerMove {
     translation: TranslationRotation,
   },
+  // Mining takes the block's break time for the tool in that hot bar slot, starting on another block drops the previous
+  // one
+  BlockBreakStart {
+    location: DDD,
+    slot: usize,
+  },
+  BlockBreakAbort,
+  // Sent once the break time is up, the server checks it against the BlockBreakStart and rolls the drops
   BlockRemove {
     location: DDD,
   },
+  // The block comes out of that slot of the player's inventory
   BlockPlace {
     location: DDD,
     block_transfer: BlockTransfer,
+    slot: usize,
   },
   RequestChunk {
     chunk_coord: DD,
   },
-  RequestFunctor {
+  // Sent when the block's interface opens, the server answers with the functor and sends it again whenever it changes
+  SubscribeFunctor {
     location: DDD,
     functor: FunctorType,
   },
+  // Sent when it closes again
+  UnsubscribeFunctor {
+    location: DDD,
+  },
   AnimationStart {
     location: DDD,
     animation: Animation,
@@ -214,4 +249,35 @@ pub enum PlayerCommand {
   InitiateInWorldCraft {
     location: DDD,
   },
+  // Swaps what the two slots hold
+  SlotMove {
+    from: InventorySlot,
+    to: InventorySlot,
+  },
+  // Moves part of a stack onto an empty slot or a stack of the same kind
+  SlotSplit {
+    from: InventorySlot,
+    to: InventorySlot,
+    quant: u32,
+  },
+  // Moves the whole stack onto a stack of the same kind
+  SlotMerge {
+    from: InventorySlot,
+    to: InventorySlot,
+  },
+}
+
+// Inventories slot transactions can move items between
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InventoryRef {
+  // The player's own
+  Player,
+  // The InternalInventory of the block entity at the location
+  Block(DDD),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InventorySlot {
+  pub inventory: InventoryRef,
+  pub slot: usize,
 }
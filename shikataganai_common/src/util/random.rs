@@ -0,0 +1,35 @@
+use crate::util::array::DDD;
+
+// Randomness for world generation. Everything is a pure function of the world seed and a position,
+// so a chunk comes out the same no matter when, where or in which order it gets generated.
+
+// SplitMix64 finalizer, every input bit affects every output bit
+fn mix(mut z: u64) -> u64 {
+  z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+  z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+  z ^ (z >> 31)
+}
+
+// Independent seed for one use of randomness, e.g. a noise layer, salts tell the uses apart
+pub fn sub_seed(seed: u64, salt: u64) -> u64 {
+  mix(seed ^ mix(salt))
+}
+
+pub fn position_random(seed: u64, salt: u64, c: DDD) -> u64 {
+  let mut z = sub_seed(seed, salt);
+  z = mix(z ^ c.0 as u32 as u64);
+  z = mix(z ^ c.1 as u32 as u64);
+  mix(z ^ c.2 as u32 as u64)
+}
+
+// Uniform over [0, 1)
+pub fn position_random_f64(seed: u64, salt: u64, c: DDD) -> f64 {
+  (position_random(seed, salt, c) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+// Stable across platforms and compiler versions unlike the std hashers
+pub fn hash_str(s: &str) -> u64 {
+  s.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+    (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+  })
+}
@@ -0,0 +1,137 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::util::array::DD;
+use crate::util::random::sub_seed;
+use crate::worldgen::feature::FeatureId;
+use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+use std::ops::Deref;
+
+const TEMPERATURE_SALT: u64 = 101;
+const HUMIDITY_SALT: u64 = 102;
+// Blocks per unit of climate noise, biomes span a few chunks
+const CLIMATE_SCALE: f64 = 256.0;
+// Height scales get averaged over this many blocks in every direction so biome borders don't turn into cliffs
+const BLEND_RADIUS: i32 = 8;
+const BLEND_STEP: i32 = 4;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash, Default, Serialize, Deserialize)]
+#[repr(u8)]
+pub enum BiomeId {
+  #[default]
+  Plains,
+  Forest,
+  Hills,
+  Barrens,
+  Mountains,
+}
+
+pub struct Biome {
+  pub name: &'static str,
+  // Multiplier for the height of the terrain above the base level
+  pub height_scale: f64,
+  pub surface: BlockId,
+  pub subsurface: BlockId,
+  // Chance for a surface block to get a decoration on top of it
+  pub decoration_density: f64,
+  // Decorations to pick from with their weights
+  pub features: &'static [(FeatureId, u32)],
+}
+
+static BIOMES: [Biome; 5] = [
+  Biome {
+    name: "Plains",
+    height_scale: 1.0,
+    surface: BlockId::GRASS,
+    subsurface: BlockId::DIRT,
+    decoration_density: 0.005,
+    features: &[(FeatureId::Tree, 8), (FeatureId::Boulder, 3), (FeatureId::Ruin, 1)],
+  },
+  Biome {
+    name: "Forest",
+    height_scale: 1.2,
+    surface: BlockId::GRASS,
+    subsurface: BlockId::DIRT,
+    decoration_density: 0.05,
+    features: &[(FeatureId::Tree, 1)],
+  },
+  Biome {
+    name: "Hills",
+    height_scale: 1.7,
+    surface: BlockId::GRASS,
+    subsurface: BlockId::DIRT,
+    decoration_density: 0.01,
+    features: &[(FeatureId::Boulder, 2), (FeatureId::Tree, 1)],
+  },
+  Biome {
+    name: "Barrens",
+    height_scale: 0.6,
+    surface: BlockId::DIRT,
+    subsurface: BlockId::COBBLE,
+    decoration_density: 0.003,
+    features: &[(FeatureId::Boulder, 4), (FeatureId::Ruin, 1)],
+  },
+  Biome {
+    name: "Mountains",
+    height_scale: 2.4,
+    surface: BlockId::COBBLE,
+    subsurface: BlockId::COBBLE,
+    decoration_density: 0.0,
+    features: &[(FeatureId::Boulder, 1)],
+  },
+];
+
+impl Deref for BiomeId {
+  type Target = Biome;
+
+  #[inline]
+  fn deref(&self) -> &'static Self::Target {
+    &BIOMES[*self as usize]
+  }
+}
+
+// Picks biomes from a temperature and a humidity noise
+pub struct BiomeMap {
+  temperature: Perlin,
+  humidity: Perlin,
+}
+
+impl BiomeMap {
+  pub fn new(seed: u64) -> Self {
+    Self {
+      temperature: Perlin::new(sub_seed(seed, TEMPERATURE_SALT) as u32),
+      humidity: Perlin::new(sub_seed(seed, HUMIDITY_SALT) as u32),
+    }
+  }
+
+  pub fn climate(&self, (x, z): DD) -> (f64, f64) {
+    let c = [x as f64 / CLIMATE_SCALE, 0.0, z as f64 / CLIMATE_SCALE];
+    (self.temperature.get(c), self.humidity.get(c))
+  }
+
+  pub fn biome(&self, column: DD) -> BiomeId {
+    let (temperature, humidity) = self.climate(column);
+    if temperature < -0.35 {
+      BiomeId::Mountains
+    } else if temperature > 0.3 && humidity < -0.1 {
+      BiomeId::Barrens
+    } else if humidity > 0.25 {
+      BiomeId::Forest
+    } else if temperature < -0.1 {
+      BiomeId::Hills
+    } else {
+      BiomeId::Plains
+    }
+  }
+
+  pub fn blended_height_scale(&self, (x, z): DD) -> f64 {
+    let mut sum = 0.0;
+    let mut count = 0;
+    for dx in (-BLEND_RADIUS..=BLEND_RADIUS).step_by(BLEND_STEP as usize) {
+      for dz in (-BLEND_RADIUS..=BLEND_RADIUS).step_by(BLEND_STEP as usize) {
+        sum += self.biome((x + dx, z + dz)).height_scale;
+        count += 1;
+      }
+    }
+    sum / count as f64
+  }
+}
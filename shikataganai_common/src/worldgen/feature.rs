@@ -0,0 +1,83 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::util::array::DDD;
+use std::ops::Deref;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Hash)]
+#[repr(u8)]
+pub enum FeatureId {
+  Tree,
+  Boulder,
+  Ruin,
+}
+
+// Block template, layers go from the bottom up, rows inside a layer along z and characters along x.
+// Characters missing from the palette, like '.', leave whatever is there alone.
+pub struct Feature {
+  pub name: &'static str,
+  pub layers: &'static [&'static [&'static str]],
+  pub palette: &'static [(char, BlockId)],
+  // Template position that ends up right on top of the surface block
+  pub origin: DDD,
+}
+
+impl Feature {
+  pub fn blocks(&self, at: DDD) -> impl Iterator<Item = (DDD, BlockId)> + '_ {
+    self.layers.iter().enumerate().flat_map(move |(y, layer)| {
+      layer.iter().enumerate().flat_map(move |(z, row)| {
+        row.chars().enumerate().filter_map(move |(x, c)| {
+          self.palette.iter().find(|(p, _)| *p == c).map(|(_, block)| {
+            (
+              (
+                at.0 + x as i32 - self.origin.0,
+                at.1 + y as i32 - self.origin.1,
+                at.2 + z as i32 - self.origin.2,
+              ),
+              *block,
+            )
+          })
+        })
+      })
+    })
+  }
+}
+
+static FEATURES: [Feature; 3] = [
+  Feature {
+    name: "Tree",
+    layers: &[
+      &[".....", ".....", "..T..", ".....", "....."],
+      &[".....", ".....", "..T..", ".....", "....."],
+      &[".....", ".....", "..T..", ".....", "....."],
+      &["LLLLL", "LLLLL", "LLTLL", "LLLLL", "LLLLL"],
+      &[".LLL.", "LLLLL", "LLTLL", "LLLLL", ".LLL."],
+      &[".....", ".LLL.", ".LLL.", ".LLL.", "....."],
+    ],
+    palette: &[('T', BlockId::DIRT), ('L', BlockId::GRASS)],
+    origin: (2, 0, 2),
+  },
+  Feature {
+    name: "Boulder",
+    layers: &[&[".CC.", "CCCC", "CCCC", ".CC."], &["....", ".CC.", ".CC.", "...."]],
+    palette: &[('C', BlockId::COBBLE)],
+    origin: (1, 0, 1),
+  },
+  Feature {
+    name: "Ruin",
+    layers: &[
+      &["CCCCC", "C...C", "C...C", "C....", "CC.CC"],
+      &["CC.CC", "C....", "....C", "C....", "C..S."],
+      &["C...C", ".....", "....C", ".....", "C...."],
+    ],
+    palette: &[('C', BlockId::COBBLE), ('S', BlockId::STAIR)],
+    origin: (2, 0, 2),
+  },
+];
+
+impl Deref for FeatureId {
+  type Target = Feature;
+
+  #[inline]
+  fn deref(&self) -> &'static Self::Target {
+    &FEATURES[*self as usize]
+  }
+}
@@ -0,0 +1,47 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::chunk::Chunk;
+use crate::util::array::{ArrayIndex, Bounds, DD, DDD};
+
+pub mod biome;
+pub mod feature;
+pub mod ore;
+pub mod perlin;
+pub mod superflat;
+pub mod void;
+
+// Turns chunk coordinates into terrain. Has to be deterministic, chunks get regenerated whenever they're missing on disk.
+pub trait WorldGenerator: Send + Sync {
+  fn generate(&self, chunk_coord: DD, registry: &BlockRegistry) -> Chunk;
+  // Second stage run on a freshly generated chunk, the blocks may reach into neighbouring chunks
+  fn decorate(&self, _chunk_coord: DD, _chunk: &Chunk) -> Vec<(DDD, BlockId)> {
+    vec![]
+  }
+}
+
+// Puts decoration blocks into the chunk wherever there's air, returns where they went and the ones that belong to other
+// chunks. The chunk's skylight is stale afterwards if anything got placed.
+pub fn place_decorations(chunk: &mut Chunk, blocks: Vec<(DDD, BlockId)>) -> (Vec<DDD>, Vec<(DDD, BlockId)>) {
+  let bounds = chunk.bounds();
+  let mut placed = vec![];
+  let mut spilled = vec![];
+  for (location, block) in blocks {
+    if location.1 < 0 || location.1 >= chunk.height() {
+      continue;
+    }
+    if !location.in_bounds(&bounds) {
+      spilled.push((location, block));
+    } else if chunk.get(location).unwrap().block == BlockId::AIR {
+      *chunk.get_mut(location).unwrap() = block.into();
+      placed.push(location);
+    }
+  }
+  (placed, spilled)
+}
+
+pub fn chunk_bounds(chunk_coord: DD, height: i32) -> Bounds<DDD> {
+  (
+    (chunk_coord.0 * 16, 0, chunk_coord.1 * 16),
+    (chunk_coord.0 * 16 + 15, height - 1, chunk_coord.1 * 16 + 15),
+  )
+}
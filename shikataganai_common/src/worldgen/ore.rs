@@ -0,0 +1,90 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::chunk::Chunk;
+use crate::util::array::{ImmediateNeighbours, DD};
+use crate::util::random::{position_random, sub_seed};
+use serde::{Deserialize, Serialize};
+
+const ORE_SALT: u64 = 13;
+
+// Settings name the blocks, generators get them resolved to the ids of their world
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OreEntry<B = BlockId> {
+  pub block: B,
+  pub min_height: i32,
+  pub max_height: i32,
+  // Steps of the random walk that lays out a vein
+  pub vein_size: u32,
+  pub veins_per_chunk: u32,
+  // Blocks the ore may replace, anything else is left alone
+  pub replaces: Vec<B>,
+}
+
+impl OreEntry<String> {
+  pub fn resolve(&self, registry: &BlockRegistry) -> Result<OreEntry, String> {
+    let id = |name: &String| registry.id(name).ok_or_else(|| format!("Unknown block {}", name));
+    Ok(OreEntry {
+      block: id(&self.block)?,
+      min_height: self.min_height,
+      max_height: self.max_height,
+      vein_size: self.vein_size,
+      veins_per_chunk: self.veins_per_chunk,
+      replaces: self.replaces.iter().map(id).collect::<Result<_, _>>()?,
+    })
+  }
+}
+
+pub fn default_ores() -> Vec<OreEntry<String>> {
+  vec![
+    OreEntry {
+      block: "iron".to_string(),
+      min_height: 0,
+      max_height: 64,
+      vein_size: 10,
+      veins_per_chunk: 48,
+      replaces: vec!["cobble".to_string()],
+    },
+    // The only source of furnace fuel
+    OreEntry {
+      block: "coal_ore".to_string(),
+      min_height: 0,
+      max_height: 64,
+      vein_size: 12,
+      veins_per_chunk: 40,
+      replaces: vec!["cobble".to_string()],
+    },
+  ]
+}
+
+// Veins start at a random spot of their chunk and wander off in random directions,
+// every chunk replays the veins of its neighbours as well so veins don't get cut off at chunk borders.
+pub fn place_ores(ores: &[OreEntry], seed: u64, chunk_coord: DD, chunk: &mut Chunk) {
+  for (index, ore) in ores.iter().enumerate() {
+    if ore.max_height < ore.min_height {
+      continue;
+    }
+    let ore_seed = sub_seed(seed, ORE_SALT.wrapping_add(index as u64));
+    let reach = (ore.vein_size as i32 + 15) / 16;
+    for cx in chunk_coord.0 - reach..=chunk_coord.0 + reach {
+      for cz in chunk_coord.1 - reach..=chunk_coord.1 + reach {
+        for vein in 0..ore.veins_per_chunk {
+          let random = |n: u32| position_random(ore_seed, n as u64, (cx, vein as i32, cz));
+          let mut c = (
+            cx * 16 + (random(0) % 16) as i32,
+            ore.min_height + (random(1) % (ore.max_height - ore.min_height + 1) as u64) as i32,
+            cz * 16 + (random(2) % 16) as i32,
+          );
+          for step in 0..ore.vein_size {
+            if c.1 >= ore.min_height
+              && c.1 <= ore.max_height
+              && chunk.get(c).is_some_and(|block| ore.replaces.contains(&block.block))
+            {
+              chunk.get_mut(c).unwrap().block = ore.block;
+            }
+            c = c.immediate_neighbours().nth((random(3 + step) % 6) as usize).unwrap();
+          }
+        }
+      }
+    }
+  }
+}
@@ -0,0 +1,151 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::chunk::Chunk;
+use crate::util::array::{Array2d, DD, DDD};
+use crate::util::random::{position_random, position_random_f64, sub_seed};
+use crate::worldgen::biome::BiomeMap;
+use crate::worldgen::ore::{place_ores, OreEntry};
+use crate::worldgen::{chunk_bounds, WorldGenerator};
+use noise::{NoiseFn, Perlin};
+
+const BOTTOM_NOISE_SALT: u64 = 11;
+const TOP_NOISE_SALT: u64 = 12;
+const TUNNEL_A_SALT: u64 = 14;
+const TUNNEL_B_SALT: u64 = 15;
+const CAVERN_SALT: u64 = 16;
+const DECORATION_SALT: u64 = 17;
+const FEATURE_SALT: u64 = 18;
+
+// Squared distance from the crossing of both tunnel noises' zero surfaces, bigger makes wider tunnels
+const TUNNEL_RADIUS: f64 = 0.012;
+const CAVERN_THRESHOLD: f64 = 0.55;
+// Caverns stay this far below the surface so they don't hollow out the ground from under the grass
+const CAVERN_MIN_DEPTH: i32 = 6;
+
+fn noise(perlin: &Perlin, c: DDD) -> f64 {
+  perlin.get([c.0 as f64 / 20.0, 0.0, c.2 as f64 / 20.0])
+}
+
+// Islands of cobble topped with their biome's surface blocks, riddled with caves
+pub struct PerlinGenerator {
+  seed: u64,
+  perlin: Perlin,
+  perlin_top: Perlin,
+  tunnel_a: Perlin,
+  tunnel_b: Perlin,
+  cavern: Perlin,
+  biome_map: BiomeMap,
+  ores: Vec<OreEntry>,
+  height: i32,
+}
+
+impl PerlinGenerator {
+  pub fn new(seed: u64, height: i32, ores: Vec<OreEntry>) -> Self {
+    Self {
+      seed,
+      perlin: Perlin::new(sub_seed(seed, BOTTOM_NOISE_SALT) as u32),
+      perlin_top: Perlin::new(sub_seed(seed, TOP_NOISE_SALT) as u32),
+      tunnel_a: Perlin::new(sub_seed(seed, TUNNEL_A_SALT) as u32),
+      tunnel_b: Perlin::new(sub_seed(seed, TUNNEL_B_SALT) as u32),
+      cavern: Perlin::new(sub_seed(seed, CAVERN_SALT) as u32),
+      biome_map: BiomeMap::new(seed),
+      ores,
+      height,
+    }
+  }
+
+  // Sampled in world coordinates, so caves run on seamlessly into the neighbouring chunks.
+  fn carved(&self, (x, y, z): DDD, surface: i32) -> bool {
+    // Two noise fields are zero on two wavy surfaces, the line where those cross is a winding tunnel.
+    let tunnel = [x as f64 / 24.0, y as f64 / 16.0, z as f64 / 24.0];
+    let a = self.tunnel_a.get(tunnel);
+    let b = self.tunnel_b.get(tunnel);
+    if a * a + b * b < TUNNEL_RADIUS {
+      return true;
+    }
+    y < surface - CAVERN_MIN_DEPTH
+      && self.cavern.get([x as f64 / 40.0, y as f64 / 24.0, z as f64 / 40.0]) > CAVERN_THRESHOLD
+  }
+}
+
+impl WorldGenerator for PerlinGenerator {
+  fn generate(&self, chunk_coord: DD, registry: &BlockRegistry) -> Chunk {
+    let (from, to) = chunk_bounds(chunk_coord, self.height);
+    let v = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |(x, z)| {
+      noise(&self.perlin, (x, 0, z))
+    });
+    let vtop = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |(x, z)| {
+      noise(&self.perlin_top, (x, 0, z))
+    });
+
+    let biomes = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |column| self.biome_map.biome(column));
+    let height_scale = Array2d::new_init(((from.0, from.2), (to.0, to.2)), |column| {
+      self.biome_map.blended_height_scale(column)
+    });
+
+    let mut chunk = Chunk::with_biomes(chunk_coord, self.height, biomes.clone(), registry, |(x, y, z)| {
+      let bottom = v[(x, z)];
+      let top = vtop[(x, z)];
+      let biome = biomes[(x, z)];
+
+      let bottom_extent = (bottom * 30.0).floor() as i32;
+      let top_extent = ((top + 1.0) * bottom / 2.0 * 30.0 * height_scale[(x, z)]).floor() as i32;
+
+      let block = if bottom <= 0.0 {
+        BlockId::AIR
+      } else if y < 30 {
+        if (30 - y) < bottom_extent {
+          BlockId::COBBLE
+        } else {
+          BlockId::AIR
+        }
+      } else if (y - 30) > top_extent {
+        BlockId::AIR
+      } else if (y - 30) == top_extent {
+        biome.surface
+      } else if y - 28 >= top_extent {
+        biome.subsurface
+      } else {
+        BlockId::COBBLE
+      };
+
+      if block != BlockId::AIR && self.carved((x, y, z), 30 + top_extent) {
+        BlockId::AIR
+      } else {
+        block
+      }
+    });
+    place_ores(&self.ores, self.seed, chunk_coord, &mut chunk);
+    chunk
+  }
+
+  fn decorate(&self, _chunk_coord: DD, chunk: &Chunk) -> Vec<(DDD, BlockId)> {
+    let mut blocks = vec![];
+    let (from, to) = chunk.bounds();
+    for x in from.0..=to.0 {
+      for z in from.2..=to.2 {
+        let biome = chunk.biomes[(x, z)];
+        let total: u32 = biome.features.iter().map(|(_, weight)| weight).sum();
+        if total == 0 || position_random_f64(self.seed, DECORATION_SALT, (x, 0, z)) >= biome.decoration_density {
+          continue;
+        }
+        let y = match (0..=to.1)
+          .rev()
+          .find(|y| chunk.get((x, *y, z)).unwrap().block != BlockId::AIR)
+        {
+          Some(y) if chunk.get((x, y, z)).unwrap().block == biome.surface => y,
+          _ => continue,
+        };
+        let mut pick = (position_random(self.seed, FEATURE_SALT, (x, 0, z)) % total as u64) as u32;
+        for (feature, weight) in biome.features {
+          if pick < *weight {
+            blocks.extend(feature.blocks((x, y + 1, z)));
+            break;
+          }
+          pick -= weight;
+        }
+      }
+    }
+    blocks
+  }
+}
@@ -0,0 +1,58 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::chunk::Chunk;
+use crate::util::array::DD;
+use crate::worldgen::WorldGenerator;
+use serde::{Deserialize, Serialize};
+
+// Named in the settings like ores are
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SuperflatLayer {
+  pub block: String,
+  pub height: i32,
+}
+
+// Horizontal layers stacked from y = 0 upwards, air above the last one
+pub struct SuperflatGenerator {
+  // Block for every y up to the top of the last layer
+  column: Vec<BlockId>,
+  height: i32,
+}
+
+impl SuperflatGenerator {
+  pub fn new(layers: &[SuperflatLayer], height: i32, registry: &BlockRegistry) -> Result<Self, String> {
+    let mut column = vec![];
+    for layer in layers {
+      let block = registry
+        .id(&layer.block)
+        .ok_or_else(|| format!("Unknown block {}", layer.block))?;
+      column.extend(std::iter::repeat_n(block, layer.height.max(0) as usize));
+    }
+    Ok(Self { column, height })
+  }
+
+  pub fn default_layers() -> Vec<SuperflatLayer> {
+    vec![
+      SuperflatLayer {
+        block: "cobble".to_string(),
+        height: 27,
+      },
+      SuperflatLayer {
+        block: "dirt".to_string(),
+        height: 2,
+      },
+      SuperflatLayer {
+        block: "grass".to_string(),
+        height: 1,
+      },
+    ]
+  }
+}
+
+impl WorldGenerator for SuperflatGenerator {
+  fn generate(&self, chunk_coord: DD, registry: &BlockRegistry) -> Chunk {
+    Chunk::new(chunk_coord, self.height, registry, |(_, y, _)| {
+      self.column.get(y as usize).copied().unwrap_or(BlockId::AIR)
+    })
+  }
+}
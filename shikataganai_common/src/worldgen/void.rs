@@ -0,0 +1,22 @@
+use crate::ecs::components::blocks::block_id::BlockId;
+use crate::ecs::components::blocks::registry::BlockRegistry;
+use crate::ecs::components::chunk::Chunk;
+use crate::util::array::DD;
+use crate::worldgen::WorldGenerator;
+
+// Nothing but air
+pub struct VoidGenerator {
+  height: i32,
+}
+
+impl VoidGenerator {
+  pub fn new(height: i32) -> Self {
+    Self { height }
+  }
+}
+
+impl WorldGenerator for VoidGenerator {
+  fn generate(&self, chunk_coord: DD, registry: &BlockRegistry) -> Chunk {
+    Chunk::new(chunk_coord, self.height, registry, |_| BlockId::AIR)
+  }
+}
@@ -1,25 +1,41 @@
-use crate::ecs::resources::world::{send_chunk_data, ServerGameWorld};
+use crate::ecs::plugins::settings::Generator;
+use crate::ecs::resources::players::{PlayerRecord, PlayerStorage, SpawnPoint};
+use crate::ecs::resources::region::RegionStorage;
+use crate::ecs::resources::subscriptions::FunctorSubscriptions;
+use crate::ecs::resources::ticks::ScheduledTicks;
+use crate::ecs::resources::world::{send_chunk_data, ChunkWatchers, DirtyChunks, ServerGameWorld};
 use crate::ecs::systems::chunkgen::collect_async_chunks;
+use crate::ecs::systems::furnace::smelt_furnaces;
+use crate::ecs::systems::functors::push_functor_updates;
+use crate::ecs::systems::inventory::{
+  give_player, handle_inventory_transactions, send_player_slots, within_reach, InventoryTransactionEvent,
+};
 use crate::ecs::systems::light::relight_system;
+use crate::ecs::systems::persistence::{autosave, save_world, SaveAllEvent};
+use crate::ecs::systems::shutdown::server_control;
+use crate::ecs::systems::ticks::{block_ticks, notify_neighbours, BlockUpdateEvent};
+use crate::ecs::systems::unload::{collect_unloaded_chunks, unload_chunks};
 use bevy::app::ScheduleRunnerSettings;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::utils::hashbrown::{HashMap, HashSet};
 use bevy_renet::renet::{RenetError, RenetServer, ServerAuthentication, ServerConfig, ServerEvent};
 use bevy_renet::RenetServerPlugin;
 use bincode::*;
-use num_traits::float::FloatConst;
 use shikataganai_common::ecs::components::blocks::block_id::BlockId;
-use shikataganai_common::ecs::components::blocks::BlockMeta;
-use shikataganai_common::ecs::components::functors::InternalInventory;
-use shikataganai_common::ecs::resources::light::{LightLevel, RelightEvent};
-use shikataganai_common::ecs::resources::player::PlayerNickname;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::{BlockMeta, BlockOrItem, QuantifiedBlockOrItem};
+use shikataganai_common::ecs::components::functors::{InternalInventory, SlotTransaction};
+use shikataganai_common::ecs::resources::light::{seed_light, RelightEvent};
+use shikataganai_common::ecs::resources::player::{PlayerNickname, HOT_BAR_WIDTH, PLAYER_INVENTORY_SIZE};
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::networking::{
-  server_connection_config, BlockTransfer, FunctorType, NetworkFrame, NetworkedEntities, PlayerCommand, PolarRotation,
+  server_connection_config, BlockTransfer, NetworkFrame, NetworkedEntities, PlayerCommand, PolarRotation,
   ServerChannel, ServerMessage, PROTOCOL_ID,
 };
 use shikataganai_common::recipes::Recipes;
 use shikataganai_common::util::array::{add_ddd, sub_ddd, DD, DDD};
+use shikataganai_common::util::random::position_random_f64;
 use std::net::UdpSocket;
 use std::time::{Duration, SystemTime};
 
@@ -29,7 +45,7 @@ pub struct ShikataganaiServerPlugin;
 pub struct FixedUpdate;
 
 #[derive(Default, Resource)]
-pub struct ServerTick(u32);
+pub struct ServerTick(pub u32);
 
 #[derive(Default, Resource)]
 pub struct PlayerEntities {
@@ -42,7 +58,23 @@ pub struct UnAuthedPlayers {
 }
 
 #[derive(Component)]
-pub struct ClientId(u64);
+pub struct ClientId(pub u64);
+
+// Clients measure the break time themselves, this much of it can get lost to latency
+const BREAK_TIME_LEEWAY: f64 = 0.2;
+
+pub struct BlockBreak {
+  location: DDD,
+  block: BlockId,
+  started: f64,
+  duration: f32,
+}
+
+// Block every client is currently mining
+#[derive(Default, Resource)]
+pub struct BlockBreaks {
+  pub breaking: HashMap<u64, BlockBreak>,
+}
 
 #[derive(Resource)]
 pub struct ShikataganaiServerAddress {
@@ -70,7 +102,10 @@ impl Plugin for ShikataganaiServerPlugin {
     .unwrap();
 
     app.add_event::<RelightEvent>();
-    app.add_event::<FunctorRequestEvent>();
+    app.add_event::<BlockUpdateEvent>();
+    app.add_event::<InventoryTransactionEvent>();
+    app.add_event::<SaveAllEvent>();
+    app.add_event::<PlayerCommandEvent>();
 
     app
       // .add_stage_after(
@@ -81,15 +116,36 @@ impl Plugin for ShikataganaiServerPlugin {
       .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(1.0 / 60.0)))
       .add_plugin(RenetServerPlugin { clear_events: false })
       .init_resource::<ServerTick>()
+      .init_resource::<BlockBreaks>()
+      .init_resource::<ScheduledTicks>()
       .init_resource::<PlayerEntities>()
       .init_resource::<UnAuthedPlayers>()
+      .init_resource::<DirtyChunks>()
+      .init_resource::<ChunkWatchers>()
+      .init_resource::<FunctorSubscriptions>()
       .insert_resource(server)
-      .add_system(handle_events)
-      .add_system(handle_functor_requests.after(handle_events))
+      .add_system(handle_connections)
+      .add_system(receive_commands.after(handle_connections))
+      .add_system(handle_auth.after(receive_commands))
+      .add_system(handle_player_updates.after(handle_auth))
+      .add_system(handle_chunk_requests.after(handle_auth))
+      .add_system(handle_subscriptions.after(handle_auth))
+      .add_system(handle_block_breaks.after(handle_auth))
+      .add_system(handle_block_placement.after(handle_block_breaks))
+      .add_system(handle_crafting.after(handle_block_placement))
+      .add_system(handle_inventory_transactions.after(handle_player_updates).after(handle_crafting))
+      .add_system(smelt_furnaces.after(handle_inventory_transactions))
+      .add_system(push_functor_updates.after(handle_subscriptions).after(smelt_furnaces))
+      .add_system(block_ticks.after(handle_crafting).before(sync_frame))
       .add_system(sync_frame)
       .add_system(collect_async_chunks)
       .add_system(panic_handler)
-      .add_system_to_stage(CoreStage::PostUpdate, relight_system);
+      .add_system(autosave)
+      .add_system(server_control)
+      .add_system(unload_chunks.after(handle_chunk_requests).after(handle_crafting))
+      .add_system(collect_unloaded_chunks)
+      .add_system_to_stage(CoreStage::PostUpdate, relight_system)
+      .add_system_to_stage(CoreStage::Last, save_world);
   }
 }
 
@@ -99,204 +155,706 @@ pub fn panic_handler(mut events: EventReader<RenetError>) {
   }
 }
 
-#[derive(Debug)]
-pub struct FunctorRequestEvent {
+// A command some client sent, receive_commands reads them off the network and each of the systems below picks out the
+// ones it handles
+pub struct PlayerCommandEvent {
   pub client: u64,
-  pub location: DDD,
-  pub entity: Entity,
-  pub functor_type: FunctorType,
+  pub command: PlayerCommand,
 }
 
-pub fn handle_functor_requests(
-  mut param_set: ParamSet<(&World, ResMut<RenetServer>)>,
-  mut functor_events: EventReader<FunctorRequestEvent>,
-) {
-  for event in functor_events.iter() {
-    let functor = serialize(match event.functor_type {
-      FunctorType::InternalInventory => {
-        if let Some(functor) = param_set.p0().get::<InternalInventory>(event.entity) {
-          functor
-        } else {
-          return;
-        }
-      }
-    })
-    .unwrap();
-    param_set.p1().send_message(
-      event.client,
-      ServerChannel::GameEvent.id(),
-      serialize(&ServerMessage::Functor {
-        location: event.location,
-        functor_type: event.functor_type,
-        functor,
-      })
-      .unwrap(),
-    );
-  }
+#[derive(SystemParam)]
+pub struct ConnectionParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  server_events: EventReader<'w, 's, ServerEvent>,
+  player_entities: ResMut<'w, PlayerEntities>,
+  unauthed_players: ResMut<'w, UnAuthedPlayers>,
+  players: Query<
+    'w,
+    's,
+    (
+      &'static Transform,
+      &'static PolarRotation,
+      &'static PlayerNickname,
+      &'static SpawnPoint,
+      &'static InternalInventory,
+    ),
+  >,
+  player_storage: Res<'w, PlayerStorage>,
+  registry: Res<'w, BlockRegistry>,
+  block_breaks: ResMut<'w, BlockBreaks>,
+  chunk_watchers: ResMut<'w, ChunkWatchers>,
+  subscriptions: ResMut<'w, FunctorSubscriptions>,
 }
 
-pub fn handle_events(
-  mut commands: Commands,
-  mut server: ResMut<RenetServer>,
-  mut server_events: EventReader<ServerEvent>,
-  mut relight: EventWriter<RelightEvent>,
-  mut functor_events: EventWriter<FunctorRequestEvent>,
-  mut player_entities: ResMut<PlayerEntities>,
-  mut unauthed_players: ResMut<UnAuthedPlayers>,
-  mut query: Query<(Entity, &mut Transform, &mut PolarRotation, &PlayerNickname)>,
-  mut game_world: ResMut<GameWorld>,
-  recipes: Res<Recipes>,
-) {
+pub fn handle_connections(params: ConnectionParams) {
+  let ConnectionParams {
+    mut commands,
+    mut server,
+    mut server_events,
+    mut player_entities,
+    mut unauthed_players,
+    players,
+    player_storage,
+    registry,
+    mut block_breaks,
+    mut chunk_watchers,
+    mut subscriptions,
+  } = params;
   for event in server_events.iter() {
     match event {
       ServerEvent::ClientConnected(client_id, _) => {
         unauthed_players.players.insert(*client_id);
         println!("Client {} connected", client_id);
+        server.send_message(
+          *client_id,
+          ServerChannel::GameEvent.id(),
+          serialize(&ServerMessage::BlockRegistry {
+            names: registry.names(),
+          })
+          .unwrap(),
+        );
       }
       ServerEvent::ClientDisconnected(client_id) => {
         println!("Client {} disconnected", client_id);
-        // let entity = player_entities.players.remove(client_id).unwrap();
-        // commands.entity(entity).despawn();
+        unauthed_players.players.remove(client_id);
+        block_breaks.breaking.remove(client_id);
+        chunk_watchers.sent.remove(client_id);
+        subscriptions.drop_client(*client_id);
+        if let Some(entity) = player_entities.players.remove(client_id) {
+          if let Ok((transform, rotation, nickname, spawn_point, inventory)) = players.get(entity)
+            && let Err(err) = player_storage.save(
+              &nickname.0,
+              &PlayerRecord::new(transform, rotation, spawn_point, inventory),
+            )
+          {
+            println!("Failed to save player {}: {}", nickname.0, err);
+          }
+          commands.entity(entity).despawn();
+          broadcast_but(
+            server.as_mut(),
+            *client_id,
+            ServerMessage::PlayerDespawn { id: *client_id },
+          );
+        }
       }
     }
   }
+}
+
+pub fn receive_commands(mut server: ResMut<RenetServer>, mut player_commands: EventWriter<PlayerCommandEvent>) {
   for client in server.clients_id().into_iter() {
     while let Some(message) = server.receive_message(client, 0) {
       let command: PlayerCommand = deserialize(&message).unwrap();
-      match command {
-        PlayerCommand::PlayerMove { translation } => {
-          let player_entity = *player_entities.players.get(&client).unwrap();
-          query.get_mut(player_entity).unwrap().1.translation = translation.0;
-          *query.get_mut(player_entity).unwrap().2 = translation.1;
-        }
-        PlayerCommand::BlockRemove { location } => {
-          if let Some(block) = game_world.get_mut(location) {
-            *block = BlockId::Air.into();
-            relight.send(RelightEvent::Relight(location));
-            broadcast_but(server.as_mut(), client, ServerMessage::BlockRemove { location })
+      player_commands.send(PlayerCommandEvent { client, command });
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct AuthParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  player_entities: ResMut<'w, PlayerEntities>,
+  unauthed_players: ResMut<'w, UnAuthedPlayers>,
+  players: Query<
+    'w,
+    's,
+    (
+      Entity,
+      &'static Transform,
+      &'static PolarRotation,
+      &'static PlayerNickname,
+      &'static InternalInventory,
+    ),
+    With<SpawnPoint>,
+  >,
+  player_storage: Res<'w, PlayerStorage>,
+}
+
+pub fn handle_auth(params: AuthParams) {
+  let AuthParams {
+    mut commands,
+    mut server,
+    mut player_commands,
+    mut player_entities,
+    mut unauthed_players,
+    players,
+    player_storage,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    if let PlayerCommand::PlayerAuth { nickname } = command
+      && unauthed_players.players.contains(&client)
+    {
+      unauthed_players.players.remove(&client);
+      let (player_entity, translation, rotation, items) = players
+        .iter()
+        .find(|(_, _, _, player_nickname, _)| player_nickname.0 == *nickname)
+        .map(|(entity, transform, rotation, _, inventory)| {
+          (entity, transform.translation, *rotation, inventory.inventory.clone())
+        })
+        .or_else(|| {
+          let record = player_storage
+            .load(nickname)
+            .unwrap_or_else(|err| {
+              println!("Failed to load player {}: {}", nickname, err);
+              None
+            })
+            .unwrap_or_default();
+          // New players and records with fewer slots get topped up with empty ones
+          let mut inventory = record.inventory;
+          if inventory.len() < PLAYER_INVENTORY_SIZE {
+            inventory.resize(PLAYER_INVENTORY_SIZE, None);
           }
-        }
-        PlayerCommand::BlockPlace { location, block_transfer } => {
-          if let Some(block) = game_world.get_mut(location) {
-            *block = block_transfer.into();
-            if block.need_to_spawn_functors() {
-              block.block.clone().spawn_or_add_functors(block, location, &mut commands);
-            }
-            relight.send(RelightEvent::Relight(location));
-            game_world.set_light_level(location, LightLevel::dark());
-            broadcast_but(server.as_mut(), client, ServerMessage::BlockPlace { location, block_transfer })
+          let player_entity = commands
+            .spawn((
+              Transform::from_translation(record.translation),
+              record.rotation,
+              SpawnPoint(record.spawn_point),
+              InternalInventory {
+                inventory: inventory.clone(),
+              },
+              ClientId(client),
+              PlayerNickname(nickname.clone()),
+            ))
+            .id();
+          Some((player_entity, record.translation, record.rotation, inventory))
+        })
+        .unwrap();
+
+      if player_entities
+        .players
+        .iter()
+        .any(|(_, entity)| *entity == player_entity)
+      {
+        println!("Client taken!");
+        continue;
+      }
+
+      for other_client in player_entities.players.keys() {
+        let other_entity = *player_entities.players.get(other_client).unwrap();
+        let (_, translation, rotation, _, _) = players.get(other_entity).unwrap();
+        server.send_message(
+          client,
+          ServerChannel::GameEvent.id(),
+          serialize(&ServerMessage::PlayerSpawn {
+            entity: other_entity,
+            id: *other_client,
+            translation: (translation.translation, *rotation),
+          })
+          .unwrap(),
+        );
+        server.send_message(
+          *other_client,
+          ServerChannel::GameEvent.id(),
+          serialize(&ServerMessage::PlayerSpawn {
+            entity: player_entity,
+            id: client,
+            translation: (translation.translation, *rotation),
+          })
+          .unwrap(),
+        );
+      }
+      server.send_message(
+        client,
+        ServerChannel::GameEvent.id(),
+        serialize(&ServerMessage::AuthConfirmed {
+          translation: (translation, rotation),
+        })
+        .unwrap(),
+      );
+      server.send_message(
+        client,
+        ServerChannel::GameEvent.id(),
+        serialize(&ServerMessage::PlayerInventory { items }).unwrap(),
+      );
+      player_entities.players.insert(client, player_entity);
+    }
+  }
+}
+
+// Movement, animations and slot moves, everything that only gets passed on
+pub fn handle_player_updates(
+  mut server: ResMut<RenetServer>,
+  mut player_commands: EventReader<PlayerCommandEvent>,
+  mut inventory_events: EventWriter<InventoryTransactionEvent>,
+  player_entities: Res<PlayerEntities>,
+  mut players: Query<(&mut Transform, &mut PolarRotation)>,
+) {
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    match command {
+      PlayerCommand::PlayerMove { translation } => {
+        let player_entity = *player_entities.players.get(&client).unwrap();
+        let (mut transform, mut rotation) = players.get_mut(player_entity).unwrap();
+        transform.translation = translation.0;
+        *rotation = translation.1;
+      }
+      PlayerCommand::AnimationStart { location, animation } => {
+        for other_client in player_entities.players.keys() {
+          if *other_client == client {
+            continue;
           }
+          server.send_message(
+            *other_client,
+            ServerChannel::GameEvent.id(),
+            serialize(&ServerMessage::AnimationStart {
+              location: *location,
+              animation: animation.clone(),
+            })
+            .unwrap(),
+          )
         }
-        PlayerCommand::RequestChunk { chunk_coord: coord } => {
-          if let Some(chunk) = game_world.get_chunk_or_spawn(coord, &mut commands, client) {
-            send_chunk_data(server.as_mut(), chunk, client);
-          }
+      }
+      PlayerCommand::SlotMove { from, to } => {
+        inventory_events.send(InventoryTransactionEvent {
+          client,
+          from: *from,
+          to: *to,
+          transaction: SlotTransaction::Move,
+        });
+      }
+      PlayerCommand::SlotSplit { from, to, quant } => {
+        inventory_events.send(InventoryTransactionEvent {
+          client,
+          from: *from,
+          to: *to,
+          transaction: SlotTransaction::Split(*quant),
+        });
+      }
+      PlayerCommand::SlotMerge { from, to } => {
+        inventory_events.send(InventoryTransactionEvent {
+          client,
+          from: *from,
+          to: *to,
+          transaction: SlotTransaction::Merge,
+        });
+      }
+      _ => {}
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct ChunkRequestParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  game_world: ResMut<'w, GameWorld>,
+  chunk_watchers: ResMut<'w, ChunkWatchers>,
+  storage: Res<'w, RegionStorage>,
+  generator: Res<'w, Generator>,
+  registry: Res<'w, BlockRegistry>,
+}
+
+pub fn handle_chunk_requests(params: ChunkRequestParams) {
+  let ChunkRequestParams {
+    mut commands,
+    mut server,
+    mut player_commands,
+    mut game_world,
+    mut chunk_watchers,
+    storage,
+    generator,
+    registry,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    if let PlayerCommand::RequestChunk { chunk_coord: coord } = *command {
+      chunk_watchers.sent.entry(*client).or_default().insert(coord);
+      if let Some(chunk) = game_world.get_chunk_or_spawn(
+        coord,
+        &mut commands,
+        Some(*client),
+        storage.as_ref(),
+        generator.as_ref(),
+        registry.as_ref(),
+      ) {
+        send_chunk_data(server.as_mut(), chunk, *client);
+      }
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct SubscriptionParams<'w, 's> {
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  player_entities: Res<'w, PlayerEntities>,
+  players: Query<'w, 's, &'static Transform, With<SpawnPoint>>,
+  game_world: Res<'w, GameWorld>,
+  subscriptions: ResMut<'w, FunctorSubscriptions>,
+}
+
+pub fn handle_subscriptions(params: SubscriptionParams) {
+  let SubscriptionParams {
+    mut player_commands,
+    player_entities,
+    players,
+    game_world,
+    mut subscriptions,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    match *command {
+      PlayerCommand::SubscribeFunctor { location, functor } => {
+        let translation = match player_entities
+          .players
+          .get(&client)
+          .and_then(|entity| players.get(*entity).ok())
+        {
+          None => continue,
+          Some(transform) => transform.translation,
+        };
+        // Same reach as for taking things out of it, push_functor_updates sends the functor
+        if let Some(entity) = game_world.get(location).map(|block| block.entity)
+          && entity != Entity::from_bits(0)
+          && within_reach(translation, location)
+        {
+          subscriptions.subscribe(location, functor, entity, client);
         }
-        PlayerCommand::RequestFunctor { location, functor } => {
-          if let Some(entity) = game_world.get(location).map(|block| block.entity) && entity != Entity::from_bits(0) {
-            functor_events.send(FunctorRequestEvent {
-              client,
-              location,
-              entity,
-              functor_type: functor
-            });
+      }
+      PlayerCommand::UnsubscribeFunctor { location } => {
+        subscriptions.unsubscribe(location, client);
+      }
+      _ => {}
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct BlockBreakParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  relight: EventWriter<'w, 's, RelightEvent>,
+  block_updates: EventWriter<'w, 's, BlockUpdateEvent>,
+  player_entities: Res<'w, PlayerEntities>,
+  players: Query<'w, 's, (&'static Transform, &'static mut InternalInventory), With<SpawnPoint>>,
+  block_inventories: Query<'w, 's, &'static InternalInventory, Without<SpawnPoint>>,
+  game_world: ResMut<'w, GameWorld>,
+  dirty_chunks: ResMut<'w, DirtyChunks>,
+  registry: Res<'w, BlockRegistry>,
+  time: Res<'w, Time>,
+  block_breaks: ResMut<'w, BlockBreaks>,
+}
+
+pub fn handle_block_breaks(params: BlockBreakParams) {
+  let BlockBreakParams {
+    mut commands,
+    mut server,
+    mut player_commands,
+    mut relight,
+    mut block_updates,
+    player_entities,
+    mut players,
+    block_inventories,
+    mut game_world,
+    mut dirty_chunks,
+    registry,
+    time,
+    mut block_breaks,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    match *command {
+      PlayerCommand::BlockBreakStart { location, slot } => {
+        let player = player_entities
+          .players
+          .get(&client)
+          .and_then(|entity| players.get(*entity).ok());
+        // Nothing gets broken from further away than a chest opens, BlockRemove is refused without the entry
+        let inventory = match player {
+          Some((transform, inventory)) if within_reach(transform.translation, location) => inventory,
+          _ => continue,
+        };
+        // The tool is whatever the server thinks is in that hot bar slot
+        let tool = match inventory.inventory.get(slot) {
+          Some(Some(QuantifiedBlockOrItem {
+            block_or_item: BlockOrItem::Item(item),
+            ..
+          }))
+            if slot < HOT_BAR_WIDTH =>
+          {
+            Some(*item)
           }
+          _ => None,
+        };
+        if let Some(block) = game_world.get(location) {
+          block_breaks.breaking.insert(
+            client,
+            BlockBreak {
+              location,
+              block: block.block,
+              started: time.elapsed_seconds_f64(),
+              duration: registry.get(block.block).break_time(tool),
+            },
+          );
         }
-        PlayerCommand::PlayerAuth { nickname } => {
-          if unauthed_players.players.contains(&client) {
-            unauthed_players.players.remove(&client);
-            let (player_entity, translation, rotation) = query.iter().find(|(_, _, _, player_nickname)| player_nickname.0 == nickname).map(|(entity, transform, rotation, _)| {
-              (entity, transform.translation, *rotation)
-            }).or_else(|| {
-              let player_entity = commands
-                .spawn((
-                   Transform::from_xyz(10.1, 45.0, 10.0),
-                   PolarRotation { phi: 0.0, theta: f32::FRAC_PI_2() },
-                   ClientId(client),
-                   PlayerNickname(nickname)
-                ))
-                .id();
-              Some((player_entity, Vec3::new(10.1, 45.0, 10.0), PolarRotation { phi: 0.0, theta: f32::FRAC_PI_2() }))
-            }).unwrap();
-
-            if player_entities.players.iter().any(|(_, entity)| *entity == player_entity) {
-              println!("Client taken!");
-              continue;
-            }
-
-            for other_client in player_entities.players.keys() {
-              let other_entity = *player_entities.players.get(other_client).unwrap();
-              let (_, translation, rotation, _) = query.get(other_entity).unwrap();
-              server.send_message(
-                client,
-                ServerChannel::GameEvent.id(),
-                serialize(&ServerMessage::PlayerSpawn {
-                  entity: other_entity,
-                  id: *other_client,
-                  translation: (translation.translation, *rotation),
-                })
-                .unwrap(),
-              );
-              server.send_message(
-                *other_client,
-                ServerChannel::GameEvent.id(),
-                serialize(&ServerMessage::PlayerSpawn {
-                  entity: player_entity,
-                  id: client,
-                  translation: (translation.translation, *rotation),
-                }).unwrap(),
-              );
-            }
+      }
+      PlayerCommand::BlockBreakAbort => {
+        block_breaks.breaking.remove(&client);
+      }
+      PlayerCommand::BlockRemove { location } => {
+        let now = time.elapsed_seconds_f64();
+        let broken = block_breaks.breaking.remove(&client).is_some_and(|block_break| {
+          block_break.location == location
+            && now - block_break.started >= block_break.duration as f64 - BREAK_TIME_LEEWAY
+            && game_world
+              .get(location)
+              .is_some_and(|block| block.block == block_break.block)
+        });
+        // Drops go straight into the inventory, the block stays if they don't all fit in there
+        let given = broken && {
+          let block = *game_world.get(location).unwrap();
+          let mut roll = 0;
+          let mut drops = registry.get(block.block).roll_drops(registry.as_ref(), || {
+            roll += 1;
+            position_random_f64(now.to_bits(), roll, location)
+          });
+          // Whatever a chest or furnace held comes out along with it
+          if block.entity != Entity::from_bits(0)
+            && let Ok(inventory) = block_inventories.get(block.entity)
+          {
+            drops.extend(inventory.inventory.iter().flatten().cloned());
+          }
+          match player_entities
+            .players
+            .get(&client)
+            .and_then(|entity| players.get_mut(*entity).ok())
+          {
+            None => false,
+            Some((_, mut inventory)) => give_player(server.as_mut(), client, inventory.as_mut(), drops),
+          }
+        };
+        if !given {
+          if broken {
+            info!(
+              "No room for the drops of {:?} in the inventory of client {}",
+              location, client
+            );
+          }
+          // The client has already removed it on its side, so it gets back what's really there
+          if let Some(block) = game_world.get(location) {
             server.send_message(
               client,
               ServerChannel::GameEvent.id(),
-              serialize(&ServerMessage::AuthConfirmed {
-                translation: (translation, rotation),
-              }).unwrap(),
+              serialize(&ServerMessage::BlockPlace {
+                location,
+                block_transfer: (*block).into(),
+              })
+              .unwrap(),
             );
-            player_entities.players.insert(client, player_entity);
           }
+          continue;
         }
-        PlayerCommand::AnimationStart { location, animation } => {
-          for other_client in player_entities.players.keys() {
-            if *other_client == client {
-              continue;
-            }
-            server.send_message(*other_client, ServerChannel::GameEvent.id(), serialize(&ServerMessage::AnimationStart { location, animation: animation.clone() }).unwrap())
+        if let Some(block) = game_world.get_mut(location) {
+          if block.entity != Entity::from_bits(0) {
+            commands.entity(block.entity).despawn();
           }
-        },
-        PlayerCommand::InitiateInWorldCraft { location } => {
-          if let Some(block) = game_world.get(location) {
-            let mut iter = vec![];
-            for r in &recipes.recipes {
-              r.from.foreach(|c, b| {
-                if *b == block.block {
-                  iter.push((location, c, r));
-                }
-              })
+          *block = BlockId::AIR.into();
+          seed_light(game_world.as_mut(), registry.as_ref(), location);
+          dirty_chunks.mark(location);
+          relight.send(RelightEvent::Relight(location));
+          notify_neighbours(&mut block_updates, location);
+          broadcast_but(server.as_mut(), client, ServerMessage::BlockRemove { location });
+        }
+      }
+      _ => {}
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct BlockPlaceParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  relight: EventWriter<'w, 's, RelightEvent>,
+  block_updates: EventWriter<'w, 's, BlockUpdateEvent>,
+  player_entities: Res<'w, PlayerEntities>,
+  players: Query<'w, 's, (&'static Transform, &'static mut InternalInventory), With<SpawnPoint>>,
+  game_world: ResMut<'w, GameWorld>,
+  dirty_chunks: ResMut<'w, DirtyChunks>,
+  registry: Res<'w, BlockRegistry>,
+}
+
+pub fn handle_block_placement(params: BlockPlaceParams) {
+  let BlockPlaceParams {
+    mut commands,
+    mut server,
+    mut player_commands,
+    mut relight,
+    mut block_updates,
+    player_entities,
+    mut players,
+    mut game_world,
+    mut dirty_chunks,
+    registry,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    if let PlayerCommand::BlockPlace {
+      location,
+      block_transfer,
+      slot,
+    } = *command
+    {
+      let (transform, mut inventory) = match player_entities
+        .players
+        .get(&client)
+        .and_then(|entity| players.get_mut(*entity).ok())
+      {
+        None => continue,
+        Some(player) => player,
+      };
+      let in_reach = within_reach(transform.translation, location);
+      let free = game_world
+        .get(location)
+        .is_some_and(|block| block.block == BlockId::AIR);
+      let placed = in_reach && free && inventory.take_one(slot, BlockOrItem::Block(block_transfer.block));
+      // The client has taken it out of the slot already, right or not
+      send_player_slots(server.as_mut(), client, &inventory, [slot]);
+      if !placed {
+        if let Some(block) = game_world.get(location) {
+          server.send_message(
+            client,
+            ServerChannel::GameEvent.id(),
+            serialize(&ServerMessage::BlockPlace {
+              location,
+              block_transfer: (*block).into(),
+            })
+            .unwrap(),
+          );
+        }
+        continue;
+      }
+      if let Some(block) = game_world.get_mut(location) {
+        *block = block_transfer.into();
+        block.sanitize_meta(registry.as_ref());
+        let block_transfer: BlockTransfer = (*block).into();
+        let definition = registry.get(block.block);
+        if definition.need_to_spawn_functors() {
+          definition.spawn_or_add_functors(block, location, &mut commands);
+        }
+        seed_light(game_world.as_mut(), registry.as_ref(), location);
+        dirty_chunks.mark(location);
+        relight.send(RelightEvent::Relight(location));
+        notify_neighbours(&mut block_updates, location);
+        // The placer gets it too, in case its meta didn't survive sanitizing
+        server.broadcast_message(
+          ServerChannel::GameEvent.id(),
+          serialize(&ServerMessage::BlockPlace {
+            location,
+            block_transfer,
+          })
+          .unwrap(),
+        );
+      }
+    }
+  }
+}
+
+#[derive(SystemParam)]
+pub struct CraftParams<'w, 's> {
+  commands: Commands<'w, 's>,
+  server: ResMut<'w, RenetServer>,
+  player_commands: EventReader<'w, 's, PlayerCommandEvent>,
+  relight: EventWriter<'w, 's, RelightEvent>,
+  block_updates: EventWriter<'w, 's, BlockUpdateEvent>,
+  player_entities: Res<'w, PlayerEntities>,
+  players: Query<'w, 's, &'static mut InternalInventory, With<SpawnPoint>>,
+  game_world: ResMut<'w, GameWorld>,
+  dirty_chunks: ResMut<'w, DirtyChunks>,
+  registry: Res<'w, BlockRegistry>,
+  recipes: Res<'w, Recipes>,
+}
+
+pub fn handle_crafting(params: CraftParams) {
+  let CraftParams {
+    mut commands,
+    mut server,
+    mut player_commands,
+    mut relight,
+    mut block_updates,
+    player_entities,
+    mut players,
+    mut game_world,
+    mut dirty_chunks,
+    registry,
+    recipes,
+  } = params;
+  for PlayerCommandEvent { client, command } in player_commands.iter() {
+    let client = *client;
+    if let PlayerCommand::InitiateInWorldCraft { location } = *command
+      && let Some(block) = game_world.get(location)
+    {
+      let mut iter = vec![];
+      for r in &recipes.recipes {
+        r.from.foreach(|c, b| {
+          if *b == block.block {
+            iter.push((location, c, r));
+          }
+        })
+      }
+      for (anchor, origin, recipe) in iter {
+        let mut flag = true;
+        recipe.from.foreach(|c, b| {
+          let loc = add_ddd(sub_ddd(c, origin), anchor);
+          flag = flag && *b == game_world.get(loc).map(|b| b.block).unwrap_or(BlockId::AIR);
+        });
+        if flag {
+          // The item goes straight into the inventory, without room for it nothing gets crafted
+          if let Some(item) = recipe.item {
+            let stacks = vec![QuantifiedBlockOrItem {
+              block_or_item: BlockOrItem::Item(item),
+              quant: 1,
+            }];
+            let given = match player_entities
+              .players
+              .get(&client)
+              .and_then(|entity| players.get_mut(*entity).ok())
+            {
+              None => false,
+              Some(mut inventory) => give_player(server.as_mut(), client, inventory.as_mut(), stacks),
+            };
+            if !given {
+              info!("No room for {:?} crafted by client {}", item, client);
+              break;
             }
-            for (anchor, origin, recipe) in iter {
-              let mut flag = true;
-              recipe.from.foreach(|c, b| {
-                let loc = add_ddd(sub_ddd(c, origin), anchor);
-                flag = flag && *b == game_world.get(loc).map(|b| b.block).unwrap_or(BlockId::Air);
-              });
-              if flag {
-                recipe.to.foreach(|c, b| {
-                  let loc = add_ddd(sub_ddd(c, origin), anchor);
-                  game_world.get_mut(loc).map(|block| {
-                    block.block = *b;
-                    server.broadcast_message(ServerChannel::GameEvent.id(), serialize(&ServerMessage::BlockPlace { location: loc, block_transfer: BlockTransfer { block: *b, meta: BlockMeta { v: 0 } } }).unwrap());
-                    relight.send(RelightEvent::Relight(loc));
-                  });
-                });
-                if let Some(item) = recipe.item {
-                  server.send_message(client, ServerChannel::GameEvent.id(), serialize(&ServerMessage::ItemAdd { item, quant: 1 }).unwrap());
-                }
-                break;
+          }
+          recipe.to.foreach(|c, b| {
+            let loc = add_ddd(sub_ddd(c, origin), anchor);
+            // Blocks the recipe keeps as they are keep their entity and whatever it holds
+            if let Some(block) = game_world.get_mut(loc)
+              && block.block != *b
+            {
+              if block.entity != Entity::from_bits(0) {
+                commands.entity(block.entity).despawn();
+              }
+              *block = (*b).into();
+              let definition = registry.get(block.block);
+              if definition.need_to_spawn_functors() {
+                definition.spawn_or_add_functors(block, loc, &mut commands);
               }
+              seed_light(game_world.as_mut(), registry.as_ref(), loc);
+              dirty_chunks.mark(loc);
+              server.broadcast_message(
+                ServerChannel::GameEvent.id(),
+                serialize(&ServerMessage::BlockPlace {
+                  location: loc,
+                  block_transfer: BlockTransfer {
+                    block: *b,
+                    meta: BlockMeta { v: 0 },
+                  },
+                })
+                .unwrap(),
+              );
+              relight.send(RelightEvent::Relight(loc));
+              notify_neighbours(&mut block_updates, loc);
             }
-          }
+          });
+          break;
         }
       }
     }
@@ -0,0 +1,167 @@
+use crate::ecs::resources::block_ids::load_world_registry;
+use crate::ecs::resources::pending::PendingBlocks;
+use crate::ecs::resources::players::PlayerStorage;
+use crate::ecs::resources::region::RegionStorage;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::chunk::{DEFAULT_WORLD_HEIGHT, SECTION_HEIGHT};
+use shikataganai_common::util::random::hash_str;
+use shikataganai_common::worldgen::ore::{default_ores, OreEntry};
+use shikataganai_common::worldgen::perlin::PerlinGenerator;
+use shikataganai_common::worldgen::superflat::{SuperflatGenerator, SuperflatLayer};
+use shikataganai_common::worldgen::void::VoidGenerator;
+use shikataganai_common::worldgen::WorldGenerator;
+use std::fs::OpenOptions;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+pub struct ServerSettingsPlugin;
+
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct ServerSettings {
+  pub world: String,
+  // Integer seed for world generation, worlds without one get a seed derived from their name.
+  // TOML integers are signed, so this is the bit pattern of the actual seed.
+  pub seed: Option<i64>,
+  pub generator: GeneratorSettings,
+  // Blocks from the bottom of the world to the top, rounded up to whole chunk sections
+  pub world_height: i32,
+  // Ore veins for generators with underground, given as [[ores]] tables
+  pub ores: Vec<OreEntry<String>>,
+  // Seconds between saves of changed chunks and online players
+  pub autosave_interval: f64,
+  // Radius in chunks around a player that keeps chunks loaded
+  pub view_distance: i32,
+  // Seconds a chunk stays loaded after the last player has left it
+  pub chunk_unload_delay: f64,
+  // Blocks picked in every loaded chunk section each server tick for random ticks, 0 turns them off
+  pub random_tick_speed: u32,
+}
+
+impl Default for ServerSettings {
+  fn default() -> Self {
+    Self {
+      world: "world".to_string(),
+      seed: None,
+      generator: GeneratorSettings::default(),
+      world_height: DEFAULT_WORLD_HEIGHT,
+      ores: default_ores(),
+      autosave_interval: 60.0,
+      view_distance: 8,
+      chunk_unload_delay: 30.0,
+      random_tick_speed: 1,
+    }
+  }
+}
+
+impl ServerSettings {
+  pub fn seed(&self) -> u64 {
+    self.seed.map_or_else(|| hash_str(&self.world), |seed| seed as u64)
+  }
+
+  pub fn world_height(&self) -> i32 {
+    (self.world_height.max(1) + SECTION_HEIGHT - 1) / SECTION_HEIGHT * SECTION_HEIGHT
+  }
+
+  pub fn build_generator(&self, registry: &BlockRegistry) -> Result<Arc<dyn WorldGenerator>, String> {
+    self
+      .generator
+      .build(self.seed(), self.world_height(), &self.ores, registry)
+  }
+}
+
+#[derive(Resource)]
+pub struct AutosaveInterval(pub Duration);
+
+// Picked in the config as e.g.
+// [generator]
+// type = "superflat"
+// layers = [{ block = "Cobble", height = 3 }, { block = "Grass", height = 1 }]
+#[derive(Default, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GeneratorSettings {
+  #[default]
+  Perlin,
+  Superflat {
+    #[serde(default = "SuperflatGenerator::default_layers")]
+    layers: Vec<SuperflatLayer>,
+  },
+  Void,
+}
+
+impl GeneratorSettings {
+  // Fails on block names the registry doesn't know
+  pub fn build(
+    &self,
+    seed: u64,
+    height: i32,
+    ores: &[OreEntry<String>],
+    registry: &BlockRegistry,
+  ) -> Result<Arc<dyn WorldGenerator>, String> {
+    Ok(match self {
+      GeneratorSettings::Perlin => {
+        let ores = ores.iter().map(|ore| ore.resolve(registry)).collect::<Result<_, _>>()?;
+        Arc::new(PerlinGenerator::new(seed, height, ores))
+      }
+      GeneratorSettings::Superflat { layers } => Arc::new(SuperflatGenerator::new(layers, height, registry)?),
+      GeneratorSettings::Void => Arc::new(VoidGenerator::new(height)),
+    })
+  }
+}
+
+#[derive(Resource, Clone)]
+pub struct Generator(pub Arc<dyn WorldGenerator>);
+
+#[derive(Resource)]
+pub struct ViewDistance(pub i32);
+
+#[derive(Resource)]
+pub struct ChunkUnloadDelay(pub Duration);
+
+#[derive(Resource)]
+pub struct RandomTickSpeed(pub u32);
+
+impl Plugin for ServerSettingsPlugin {
+  fn build(&self, app: &mut App) {
+    let mut file = OpenOptions::new()
+      .write(true)
+      .read(true)
+      .create(true)
+      .truncate(false)
+      .open("shikataganai_server.toml")
+      .unwrap();
+    let mut str = String::new();
+    file.read_to_string(&mut str).unwrap();
+    // Running on defaults instead of a config that doesn't parse would open or generate some other world.
+    // Block names in the settings get looked up in the world's registry, which has to be loaded first.
+    let toml = toml::from_str::<toml::Value>(str.as_str()).unwrap_or_else(|err| panic!("Bad server settings: {}", err));
+    let world = toml
+      .get("world")
+      .and_then(|world| world.as_str())
+      .map_or_else(|| ServerSettings::default().world, String::from);
+    let registry = load_world_registry(Path::new(&world));
+    let toml: ServerSettings = toml
+      .try_into()
+      .unwrap_or_else(|err| panic!("Bad server settings: {}", err));
+    app.insert_resource(AutosaveInterval(Duration::from_secs_f64(toml.autosave_interval)));
+    let generator = toml
+      .build_generator(&registry)
+      .unwrap_or_else(|err| panic!("Bad generator settings: {}", err));
+    app.insert_resource(Generator(generator));
+    app.insert_resource(registry);
+    app.insert_resource(ViewDistance(toml.view_distance));
+    app.insert_resource(ChunkUnloadDelay(Duration::from_secs_f64(toml.chunk_unload_delay)));
+    app.insert_resource(RandomTickSpeed(toml.random_tick_speed));
+    let storage = RegionStorage::new(&toml.world, toml.world_height());
+    app.insert_resource(PendingBlocks::load(storage.root()).unwrap_or_else(|err| {
+      println!("Failed to load pending blocks: {}", err);
+      PendingBlocks::default()
+    }));
+    app.insert_resource(storage);
+    app.insert_resource(PlayerStorage::new(&toml.world));
+  }
+}
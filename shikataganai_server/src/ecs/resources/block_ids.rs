@@ -0,0 +1,110 @@
+use shikataganai_common::ecs::components::blocks::registry::{load_definitions, BlockRegistry, BLOCK_DEFINITIONS_DIR};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub const BLOCK_IDS_FORMAT_VERSION: u32 = 1;
+const BLOCK_IDS_FILE: &str = "block_ids";
+
+// Block names of a world indexed by id, the ids in its chunks mean nothing without them.
+// Worlds from before the registry don't have the file, their ids match the order of the core definitions.
+#[derive(Default)]
+pub struct BlockIds {
+  pub names: Vec<String>,
+}
+
+impl BlockIds {
+  pub fn load(root: &Path) -> Result<Self> {
+    let path = root.join(BLOCK_IDS_FILE);
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+    let data = std::fs::read(path)?;
+    if data.len() < 4 {
+      return Err(Error::new(ErrorKind::InvalidData, "Truncated block ids"));
+    }
+    match u32::from_le_bytes(data[0..4].try_into().unwrap()) {
+      BLOCK_IDS_FORMAT_VERSION => Ok(Self {
+        names: bincode::deserialize(&data[4..]).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+      }),
+      version => Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported block ids format version {}", version),
+      )),
+    }
+  }
+
+  pub fn save(&self, root: &Path) -> Result<()> {
+    let path = root.join(BLOCK_IDS_FILE);
+    let mut data = BLOCK_IDS_FORMAT_VERSION.to_le_bytes().to_vec();
+    data.extend(bincode::serialize(&self.names).unwrap());
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(temporary, path)
+  }
+}
+
+// Blocks keep the ids the world gave them, newly defined ones get the next free ids
+pub fn load_world_registry(root: &Path) -> BlockRegistry {
+  std::fs::create_dir_all(root).unwrap();
+  // Assigning ids anew would scramble every chunk of the world, better not to start at all.
+  let block_ids = BlockIds::load(root).expect("Failed to load block ids");
+  let registry = BlockRegistry::assign(load_definitions(Path::new(BLOCK_DEFINITIONS_DIR)), &block_ids.names);
+  BlockIds {
+    names: registry.names(),
+  }
+  .save(root)
+  .unwrap();
+  registry
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+  use shikataganai_common::ecs::components::blocks::registry::core_definitions;
+  use std::path::PathBuf;
+
+  fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shikataganai_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  #[test]
+  fn ids_survive_reloads() {
+    let world = scratch_dir("block_ids_world");
+    let mods = scratch_dir("block_ids_mods");
+    assert!(BlockIds::load(&world).unwrap().names.is_empty());
+
+    // A world that had a mod block registered before the later core blocks came along
+    let core: Vec<String> = core_definitions()
+      .into_iter()
+      .map(|definition| definition.name)
+      .collect();
+    let mut names = core[..BlockId::CONSTANT_IDS as usize].to_vec();
+    names.push("marble".to_string());
+    names.extend(core[BlockId::CONSTANT_IDS as usize..].iter().cloned());
+    BlockIds { names: names.clone() }.save(&world).unwrap();
+    assert_eq!(BlockIds::load(&world).unwrap().names, names);
+
+    std::fs::write(
+      mods.join("stone.toml"),
+      "[[block]]\nname = \"marble\"\n\n[[block]]\nname = \"basalt\"\n",
+    )
+    .unwrap();
+    let registry = BlockRegistry::assign(load_definitions(&mods), &BlockIds::load(&world).unwrap().names);
+    for (id, name) in names.iter().enumerate() {
+      assert_eq!(registry.id(name), Some(BlockId(id as u32)));
+    }
+    assert_eq!(registry.id("basalt"), Some(BlockId(names.len() as u32)));
+
+    // Without their definitions the mod blocks stay as placeholders and nothing moves
+    std::fs::remove_file(mods.join("stone.toml")).unwrap();
+    let reloaded = BlockRegistry::assign(load_definitions(&mods), &registry.names());
+    assert_eq!(reloaded.names(), registry.names());
+
+    std::fs::remove_dir_all(world).unwrap();
+    std::fs::remove_dir_all(mods).unwrap();
+  }
+}
@@ -0,0 +1,28 @@
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+// Requests coming from outside of the schedule, e.g. signal handlers or the server console.
+#[derive(Clone, Default, Resource)]
+pub struct ServerControl {
+  shutdown: Arc<AtomicBool>,
+  save_all: Arc<AtomicBool>,
+}
+
+impl ServerControl {
+  pub fn request_shutdown(&self) {
+    self.shutdown.store(true, Ordering::SeqCst);
+  }
+
+  pub fn request_save_all(&self) {
+    self.save_all.store(true, Ordering::SeqCst);
+  }
+
+  pub fn take_shutdown(&self) -> bool {
+    self.shutdown.swap(false, Ordering::SeqCst)
+  }
+
+  pub fn take_save_all(&self) -> bool {
+    self.save_all.swap(false, Ordering::SeqCst)
+  }
+}
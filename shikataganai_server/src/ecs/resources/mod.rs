@@ -1 +1,8 @@
+pub mod block_ids;
+pub mod control;
+pub mod pending;
+pub mod players;
+pub mod region;
+pub mod subscriptions;
+pub mod ticks;
 pub mod world;
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+use shikataganai_common::util::array::{DD, DDD};
+use std::io::{Error, ErrorKind, Result};
+use std::path::Path;
+
+pub const PENDING_FORMAT_VERSION: u32 = 1;
+const PENDING_FILE: &str = "pending_blocks";
+
+// Decoration blocks that spilled over into chunks which weren't around at the time,
+// they get placed once their chunk is generated or loaded.
+#[derive(Default, Resource)]
+pub struct PendingBlocks {
+  pub blocks: HashMap<DD, Vec<(DDD, BlockId)>>,
+}
+
+impl PendingBlocks {
+  pub fn load(root: &Path) -> Result<Self> {
+    let path = root.join(PENDING_FILE);
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+    let data = std::fs::read(path)?;
+    if data.len() < 4 {
+      return Err(Error::new(ErrorKind::InvalidData, "Truncated pending blocks"));
+    }
+    match u32::from_le_bytes(data[0..4].try_into().unwrap()) {
+      PENDING_FORMAT_VERSION => {
+        let blocks: Vec<(DD, Vec<(DDD, BlockId)>)> =
+          bincode::deserialize(&data[4..]).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+        Ok(Self {
+          blocks: blocks.into_iter().collect(),
+        })
+      }
+      version => Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported pending blocks format version {}", version),
+      )),
+    }
+  }
+
+  pub fn save(&self, root: &Path) -> Result<()> {
+    let path = root.join(PENDING_FILE);
+    let mut data = PENDING_FORMAT_VERSION.to_le_bytes().to_vec();
+    data.extend(bincode::serialize(&self.blocks.iter().collect::<Vec<_>>()).unwrap());
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(temporary, path)
+  }
+}
@@ -0,0 +1,171 @@
+use bevy::prelude::*;
+use num_traits::FloatConst;
+use serde::{Deserialize, Serialize};
+use shikataganai_common::ecs::components::blocks::{BlockOrItem, QuantifiedBlockOrItem};
+use shikataganai_common::ecs::components::functors::InternalInventory;
+use shikataganai_common::ecs::components::item::ItemId;
+use shikataganai_common::networking::PolarRotation;
+use std::io::{Error, ErrorKind, Result};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub const PLAYER_FORMAT_VERSION: u32 = 1;
+pub const DEFAULT_SPAWN_POINT: Vec3 = Vec3::new(10.1, 45.0, 10.0);
+
+#[derive(Component, Copy, Clone)]
+pub struct SpawnPoint(pub Vec3);
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerRecord {
+  pub translation: Vec3,
+  pub rotation: PolarRotation,
+  pub spawn_point: Vec3,
+  pub inventory: Vec<Option<QuantifiedBlockOrItem>>,
+}
+
+impl Default for PlayerRecord {
+  fn default() -> Self {
+    Self {
+      translation: DEFAULT_SPAWN_POINT,
+      rotation: PolarRotation {
+        phi: 0.0,
+        theta: f32::FRAC_PI_2(),
+      },
+      spawn_point: DEFAULT_SPAWN_POINT,
+      // New players start out with just a pickaxe, everything else has to be mined or crafted
+      inventory: vec![Some(QuantifiedBlockOrItem {
+        block_or_item: BlockOrItem::Item(ItemId::Pickaxe),
+        quant: 1,
+      })],
+    }
+  }
+}
+
+impl PlayerRecord {
+  pub fn new(
+    transform: &Transform,
+    rotation: &PolarRotation,
+    spawn_point: &SpawnPoint,
+    inventory: &InternalInventory,
+  ) -> Self {
+    Self {
+      translation: transform.translation,
+      rotation: *rotation,
+      spawn_point: spawn_point.0,
+      inventory: inventory.inventory.clone(),
+    }
+  }
+}
+
+#[derive(Clone, Resource)]
+pub struct PlayerStorage {
+  root: Arc<PathBuf>,
+}
+
+impl PlayerStorage {
+  pub fn new(root: impl Into<PathBuf>) -> Self {
+    let root = root.into().join("players");
+    std::fs::create_dir_all(&root).unwrap();
+    Self { root: Arc::new(root) }
+  }
+
+  // Nicknames are arbitrary user input, escape everything that could misbehave in a file name.
+  fn player_path(&self, nickname: &str) -> PathBuf {
+    let mut name = String::new();
+    for byte in nickname.bytes() {
+      if byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_' {
+        name.push(byte as char);
+      } else {
+        name.push_str(&format!("%{:02X}", byte));
+      }
+    }
+    self.root.join(format!("{}.player", name))
+  }
+
+  pub fn load(&self, nickname: &str) -> Result<Option<PlayerRecord>> {
+    let path = self.player_path(nickname);
+    if !path.exists() {
+      return Ok(None);
+    }
+    let data = std::fs::read(path)?;
+    if data.len() < 4 {
+      return Err(Error::new(ErrorKind::InvalidData, "Truncated player record"));
+    }
+    match u32::from_le_bytes(data[0..4].try_into().unwrap()) {
+      PLAYER_FORMAT_VERSION => bincode::deserialize(&data[4..])
+        .map(Some)
+        .map_err(|err| Error::new(ErrorKind::InvalidData, err)),
+      version => Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported player format version {}", version),
+      )),
+    }
+  }
+
+  pub fn save(&self, nickname: &str, record: &PlayerRecord) -> Result<()> {
+    let path = self.player_path(nickname);
+    let mut data = PLAYER_FORMAT_VERSION.to_le_bytes().to_vec();
+    data.extend(bincode::serialize(record).unwrap());
+    // Write next to the old record first so a crash mid-write doesn't lose it.
+    let temporary = path.with_extension("player.tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(temporary, path)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+
+  #[test]
+  fn records_load_back_by_nickname() {
+    let root = std::env::temp_dir().join(format!("shikataganai_players_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let storage = PlayerStorage::new(&root);
+    let record = PlayerRecord {
+      translation: Vec3::new(1.5, 70.0, -3.0),
+      rotation: PolarRotation { phi: 0.25, theta: 1.5 },
+      spawn_point: Vec3::new(-8.0, 64.0, 2.0),
+      inventory: vec![
+        None,
+        Some(QuantifiedBlockOrItem {
+          block_or_item: BlockOrItem::Block(BlockId::DIRT),
+          quant: 12,
+        }),
+      ],
+    };
+    // None of these may end up in the same file, or anywhere outside the players directory
+    let nicknames = ["steve", "../steve", "st%65ve", "ste ve", "スティーブ"];
+    for nickname in nicknames {
+      assert!(storage.load(nickname).unwrap().is_none());
+    }
+    storage.save("../steve", &record).unwrap();
+    for nickname in nicknames {
+      assert_eq!(storage.load(nickname).unwrap().is_some(), nickname == "../steve");
+    }
+    let loaded = storage.load("../steve").unwrap().unwrap();
+    assert_eq!(
+      bincode::serialize(&loaded).unwrap(),
+      bincode::serialize(&record).unwrap()
+    );
+    assert_eq!(std::fs::read_dir(root.join("players")).unwrap().count(), 1);
+    std::fs::remove_dir_all(root).unwrap();
+  }
+
+  #[test]
+  fn unknown_versions_are_refused() {
+    let root = std::env::temp_dir().join(format!("shikataganai_players_version_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&root);
+    let storage = PlayerStorage::new(&root);
+    storage.save("steve", &PlayerRecord::default()).unwrap();
+    let path = storage.player_path("steve");
+    let mut data = std::fs::read(&path).unwrap();
+    data[0..4].copy_from_slice(&(PLAYER_FORMAT_VERSION + 1).to_le_bytes());
+    std::fs::write(&path, data).unwrap();
+    assert!(storage.load("steve").is_err());
+    std::fs::write(&path, [1, 0]).unwrap();
+    assert!(storage.load("steve").is_err());
+    std::fs::remove_dir_all(root).unwrap();
+  }
+}
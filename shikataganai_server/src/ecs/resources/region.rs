@@ -0,0 +1,476 @@
+use bevy::prelude::*;
+use bevy::utils::hashbrown::HashMap;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use serde::Deserialize;
+use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::Block;
+use shikataganai_common::ecs::components::chunk::{section_bounds, Chunk, Section, SECTION_HEIGHT};
+use shikataganai_common::ecs::components::functors::SavedFunctor;
+use shikataganai_common::ecs::resources::light::LightLevel;
+use shikataganai_common::util::array::{Array, Array2d, Array3d, DD, DDD};
+use shikataganai_common::worldgen::biome::BiomeId;
+use shikataganai_common::worldgen::{place_decorations, WorldGenerator};
+use std::fs::{File, OpenOptions};
+use std::io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+// Region file layout:
+// [magic: 4][region format version: u32][REGION_CHUNKS entries of (offset: u64, length: u32, chunk version: u32)][chunk blobs...]
+// Every chunk blob is zlib compressed bincode in the format given by its entry's chunk version.
+// An entry with zero length means the chunk has never been saved.
+pub const REGION_SIZE: i32 = 32;
+pub const REGION_FORMAT_VERSION: u32 = 1;
+// 1: bare chunk
+// 2: chunk followed by the functors of its block entities
+// 3: chunks keep the biome of every column
+// 4: chunks are split into vertical sections, all air sections aren't stored
+pub const CHUNK_FORMAT_VERSION: u32 = 4;
+
+const REGION_MAGIC: [u8; 4] = *b"SKRG";
+const REGION_CHUNKS: usize = (REGION_SIZE * REGION_SIZE) as usize;
+const REGION_ENTRY_LENGTH: usize = 16;
+const REGION_HEADER_LENGTH: u64 = 8 + (REGION_CHUNKS * REGION_ENTRY_LENGTH) as u64;
+
+#[derive(Copy, Clone, Default)]
+struct RegionEntry {
+  offset: u64,
+  length: u32,
+  version: u32,
+}
+
+pub struct SavedChunk {
+  pub chunk: Chunk,
+  pub functors: Vec<(DDD, SavedFunctor)>,
+}
+
+impl SavedChunk {
+  // Also returns the decoration blocks that belong to other chunks
+  pub fn generate(
+    chunk_coord: DD,
+    generator: &dyn WorldGenerator,
+    registry: &BlockRegistry,
+  ) -> (Self, Vec<(DDD, BlockId)>) {
+    let mut chunk = generator.generate(chunk_coord, registry);
+    let decorations = generator.decorate(chunk_coord, &chunk);
+    let (placed, spilled) = place_decorations(&mut chunk, decorations);
+    if !placed.is_empty() {
+      chunk.relight_sky(registry);
+    }
+    (
+      Self {
+        chunk,
+        functors: vec![],
+      },
+      spilled,
+    )
+  }
+}
+
+#[derive(Clone, Resource)]
+pub struct RegionStorage {
+  root: Arc<PathBuf>,
+  // Chunks are loaded from async tasks while saving happens on the main schedule, region files are shared between both.
+  lock: Arc<Mutex<()>>,
+  // Chunks saved while the world was lower get topped up with empty sections
+  height: i32,
+}
+
+impl RegionStorage {
+  pub fn new(root: impl Into<PathBuf>, height: i32) -> Self {
+    let root = root.into();
+    std::fs::create_dir_all(root.join("region")).unwrap();
+    Self {
+      root: Arc::new(root),
+      lock: Arc::new(Mutex::new(())),
+      height,
+    }
+  }
+
+  pub fn root(&self) -> &PathBuf {
+    &self.root
+  }
+
+  pub fn region_coord(chunk_coord: DD) -> DD {
+    (
+      chunk_coord.0.div_euclid(REGION_SIZE),
+      chunk_coord.1.div_euclid(REGION_SIZE),
+    )
+  }
+
+  fn region_index(chunk_coord: DD) -> usize {
+    (chunk_coord.1.rem_euclid(REGION_SIZE) * REGION_SIZE + chunk_coord.0.rem_euclid(REGION_SIZE)) as usize
+  }
+
+  fn region_path(&self, region_coord: DD) -> PathBuf {
+    self
+      .root
+      .join("region")
+      .join(format!("r.{}.{}.region", region_coord.0, region_coord.1))
+  }
+
+  pub fn load_chunk(&self, chunk_coord: DD) -> Result<Option<SavedChunk>> {
+    let _guard = self.lock.lock().unwrap();
+    let path = self.region_path(Self::region_coord(chunk_coord));
+    if !path.exists() {
+      return Ok(None);
+    }
+    let mut file = File::open(path)?;
+    let entry = read_header(&mut file)?[Self::region_index(chunk_coord)];
+    if entry.length == 0 {
+      return Ok(None);
+    }
+    let mut data = vec![0; entry.length as usize];
+    file.seek(SeekFrom::Start(entry.offset))?;
+    file.read_exact(&mut data)?;
+    let mut decoder = ZlibDecoder::new(data.as_slice());
+    let mut data = Vec::new();
+    decoder.read_to_end(&mut data)?;
+    let mut saved_chunk = decode_chunk(entry.version, &data)?;
+    let sections = (self.height / SECTION_HEIGHT) as usize;
+    if saved_chunk.chunk.sections.len() < sections {
+      saved_chunk.chunk.sections.resize_with(sections, || None);
+    }
+    Ok(Some(saved_chunk))
+  }
+
+  pub fn save_chunks<'a>(&self, chunks: impl Iterator<Item = (DD, &'a Chunk, Vec<(DDD, SavedFunctor)>)>) -> Result<()> {
+    self.write_chunks(chunks.map(|(chunk_coord, chunk, functors)| (chunk_coord, encode_chunk(chunk, functors))))
+  }
+
+  // Takes blobs made by `encode_chunk`, so the writing can happen away from the chunk data
+  pub fn write_chunks(&self, chunks: impl Iterator<Item = (DD, Vec<u8>)>) -> Result<()> {
+    let mut regions: HashMap<DD, Vec<(DD, Vec<u8>)>> = HashMap::new();
+    for (chunk_coord, data) in chunks {
+      regions
+        .entry(Self::region_coord(chunk_coord))
+        .or_default()
+        .push((chunk_coord, data));
+    }
+    let _guard = self.lock.lock().unwrap();
+    for (region_coord, chunks) in regions {
+      let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(self.region_path(region_coord))?;
+      let mut header = if file.metadata()?.len() < REGION_HEADER_LENGTH {
+        let header = vec![RegionEntry::default(); REGION_CHUNKS];
+        write_header(&mut file, &header)?;
+        header
+      } else {
+        read_header(&mut file)?
+      };
+      for (chunk_coord, data) in chunks {
+        let entry = &mut header[Self::region_index(chunk_coord)];
+        // Reuse the old slot if the chunk still fits, otherwise append it to the end of the file.
+        let offset = if entry.length != 0 && data.len() <= entry.length as usize {
+          file.seek(SeekFrom::Start(entry.offset))?
+        } else {
+          file.seek(SeekFrom::End(0))?
+        };
+        file.write_all(&data)?;
+        *entry = RegionEntry {
+          offset,
+          length: data.len() as u32,
+          version: CHUNK_FORMAT_VERSION,
+        };
+      }
+      write_header(&mut file, &header)?;
+      file.sync_data()?;
+    }
+    Ok(())
+  }
+}
+
+pub fn encode_chunk(chunk: &Chunk, functors: Vec<(DDD, SavedFunctor)>) -> Vec<u8> {
+  let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+  encoder
+    .write_all(&bincode::serialize(&(chunk, functors)).unwrap())
+    .unwrap();
+  encoder.finish().unwrap()
+}
+
+fn read_header(file: &mut File) -> Result<Vec<RegionEntry>> {
+  let mut data = vec![0; REGION_HEADER_LENGTH as usize];
+  file.seek(SeekFrom::Start(0))?;
+  file.read_exact(&mut data)?;
+  if data[0..4] != REGION_MAGIC {
+    return Err(Error::new(ErrorKind::InvalidData, "Not a region file"));
+  }
+  let version = u32::from_le_bytes(data[4..8].try_into().unwrap());
+  if version != REGION_FORMAT_VERSION {
+    return Err(Error::new(
+      ErrorKind::InvalidData,
+      format!("Unsupported region format version {}", version),
+    ));
+  }
+  Ok(
+    data[8..]
+      .chunks_exact(REGION_ENTRY_LENGTH)
+      .map(|entry| RegionEntry {
+        offset: u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+        length: u32::from_le_bytes(entry[8..12].try_into().unwrap()),
+        version: u32::from_le_bytes(entry[12..16].try_into().unwrap()),
+      })
+      .collect(),
+  )
+}
+
+fn write_header(file: &mut File, header: &[RegionEntry]) -> Result<()> {
+  let mut data = Vec::with_capacity(REGION_HEADER_LENGTH as usize);
+  data.extend_from_slice(&REGION_MAGIC);
+  data.extend_from_slice(&REGION_FORMAT_VERSION.to_le_bytes());
+  for entry in header {
+    data.extend_from_slice(&entry.offset.to_le_bytes());
+    data.extend_from_slice(&entry.length.to_le_bytes());
+    data.extend_from_slice(&entry.version.to_le_bytes());
+  }
+  file.seek(SeekFrom::Start(0))?;
+  file.write_all(&data)
+}
+
+// Chunk layout before biomes, those chunks end up with the default biome everywhere
+#[derive(Deserialize)]
+struct ChunkV2 {
+  grid: Array3d<Block>,
+  light_map: Array3d<LightLevel>,
+}
+
+impl From<ChunkV2> for ChunkV3 {
+  fn from(chunk: ChunkV2) -> Self {
+    let ((from_x, _, from_z), (to_x, _, to_z)) = chunk.grid.bounds;
+    ChunkV3 {
+      grid: chunk.grid,
+      light_map: chunk.light_map,
+      biomes: Array2d::new_init(((from_x, from_z), (to_x, to_z)), |_| BiomeId::default()),
+    }
+  }
+}
+
+// Chunk layout before sections, a single grid from the bottom of the world to the top
+#[derive(Deserialize)]
+struct ChunkV3 {
+  grid: Array3d<Block>,
+  light_map: Array3d<LightLevel>,
+  biomes: Array2d<BiomeId>,
+}
+
+impl From<ChunkV3> for Chunk {
+  fn from(chunk: ChunkV3) -> Self {
+    let (from, to) = chunk.grid.bounds;
+    let chunk_coord = (from.0.div_euclid(16), from.2.div_euclid(16));
+    let sections = (0..(to.1 + 1) / SECTION_HEIGHT)
+      .map(|index| {
+        let bounds = section_bounds(chunk_coord, index as usize);
+        Some(Section {
+          grid: Array::new_init(bounds, |c| chunk.grid[c]),
+          light_map: Array::new_init(bounds, |c| chunk.light_map[c]),
+        })
+      })
+      .collect();
+    let mut chunk = Chunk {
+      coord: chunk_coord,
+      sections,
+      biomes: chunk.biomes,
+    };
+    chunk.compact();
+    chunk
+  }
+}
+
+// Older chunk versions get migrated here once the chunk format changes.
+fn decode_chunk(version: u32, data: &[u8]) -> Result<SavedChunk> {
+  let (mut chunk, functors): (Chunk, Vec<(DDD, SavedFunctor)>) = match version {
+    1 => (
+      ChunkV3::from(bincode::deserialize::<ChunkV2>(data).map_err(|err| Error::new(ErrorKind::InvalidData, err))?)
+        .into(),
+      vec![],
+    ),
+    2 => {
+      let (chunk, functors): (ChunkV2, _) =
+        bincode::deserialize(data).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+      (ChunkV3::from(chunk).into(), functors)
+    }
+    3 => {
+      let (chunk, functors): (ChunkV3, _) =
+        bincode::deserialize(data).map_err(|err| Error::new(ErrorKind::InvalidData, err))?;
+      (chunk.into(), functors)
+    }
+    CHUNK_FORMAT_VERSION => bincode::deserialize(data).map_err(|err| Error::new(ErrorKind::InvalidData, err))?,
+    version => {
+      return Err(Error::new(
+        ErrorKind::InvalidData,
+        format!("Unsupported chunk format version {}", version),
+      ))
+    }
+  };
+  // Entities from the previous run mean nothing now, block entities get respawned from the saved functors.
+  chunk.clear_entities();
+  Ok(SavedChunk { chunk, functors })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use shikataganai_common::ecs::components::chunk::DEFAULT_WORLD_HEIGHT;
+  use shikataganai_common::ecs::components::functors::InternalInventory;
+  use shikataganai_common::worldgen::ore::default_ores;
+  use shikataganai_common::worldgen::perlin::PerlinGenerator;
+
+  fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("shikataganai_{}_{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+  }
+
+  // Cobble up to a height that changes from column to column
+  fn hills(chunk_coord: DD) -> Chunk {
+    chunk(chunk_coord, |(x, y, z)| y < 40 + (x * 7 + z * 3).rem_euclid(11))
+  }
+
+  // Cobble and air all over the place, compresses a lot worse than hills
+  fn rubble(chunk_coord: DD) -> Chunk {
+    chunk(chunk_coord, |(x, y, z)| {
+      ((x.wrapping_mul(73856093)) ^ (y.wrapping_mul(19349663)) ^ (z.wrapping_mul(83492791))).rem_euclid(3) == 0
+    })
+  }
+
+  fn chunk(chunk_coord: DD, cobble: impl Fn(DDD) -> bool) -> Chunk {
+    let block_f = |c| if cobble(c) { BlockId::COBBLE } else { BlockId::AIR };
+    Chunk::new(chunk_coord, DEFAULT_WORLD_HEIGHT, &BlockRegistry::default(), block_f)
+  }
+
+  fn bytes(chunk: &Chunk) -> Vec<u8> {
+    bincode::serialize(chunk).unwrap()
+  }
+
+  #[test]
+  fn saved_chunks_load_back() {
+    let root = scratch_dir("region_load");
+    let storage = RegionStorage::new(&root, DEFAULT_WORLD_HEIGHT);
+    // The last one lands in another region
+    let chunk_coords = [(0, 0), (1, 0), (-1, 5), (REGION_SIZE, 3)];
+    let chunks: Vec<Chunk> = chunk_coords.iter().map(|&chunk_coord| hills(chunk_coord)).collect();
+    storage
+      .save_chunks(
+        chunk_coords
+          .iter()
+          .zip(chunks.iter())
+          .map(|(&chunk_coord, chunk)| (chunk_coord, chunk, vec![])),
+      )
+      .unwrap();
+    for (&chunk_coord, chunk) in chunk_coords.iter().zip(chunks.iter()) {
+      assert_eq!(
+        bytes(&storage.load_chunk(chunk_coord).unwrap().unwrap().chunk),
+        bytes(chunk)
+      );
+    }
+    assert!(storage.load_chunk((2, 0)).unwrap().is_none());
+    assert!(storage.load_chunk((-REGION_SIZE, 0)).unwrap().is_none());
+    std::fs::remove_dir_all(root).unwrap();
+  }
+
+  #[test]
+  fn grown_chunks_move_to_the_end() {
+    let root = scratch_dir("region_grow");
+    let storage = RegionStorage::new(&root, DEFAULT_WORLD_HEIGHT);
+    let (first, second) = (hills((0, 0)), hills((1, 0)));
+    storage
+      .save_chunks([((0, 0), &first, vec![]), ((1, 0), &second, vec![])].into_iter())
+      .unwrap();
+    // No longer fits its old slot, the chunk saved right after it has to stay intact
+    let grown = rubble((0, 0));
+    storage.save_chunks([((0, 0), &grown, vec![])].into_iter()).unwrap();
+    assert_eq!(
+      bytes(&storage.load_chunk((0, 0)).unwrap().unwrap().chunk),
+      bytes(&grown)
+    );
+    assert_eq!(
+      bytes(&storage.load_chunk((1, 0)).unwrap().unwrap().chunk),
+      bytes(&second)
+    );
+    // Shrinking back reuses the slot at the end
+    storage.save_chunks([((0, 0), &first, vec![])].into_iter()).unwrap();
+    assert_eq!(
+      bytes(&storage.load_chunk((0, 0)).unwrap().unwrap().chunk),
+      bytes(&first)
+    );
+    assert_eq!(
+      bytes(&storage.load_chunk((1, 0)).unwrap().unwrap().chunk),
+      bytes(&second)
+    );
+    std::fs::remove_dir_all(root).unwrap();
+  }
+
+  #[test]
+  fn old_chunk_versions_are_migrated() {
+    // A single grid over the whole height like before sections, cobble reaches into the second section only
+    let bounds = ((32, 0, -48), (47, 127, -33));
+    let cobble = |(x, y, z): DDD| y < 20 + (x + z).rem_euclid(4);
+    let grid = Array3d::new_init(bounds, |c| {
+      Block::new(if cobble(c) { BlockId::COBBLE } else { BlockId::AIR })
+    });
+    let light_map = Array3d::new_init(bounds, |c| LightLevel::new(if cobble(c) { 0 } else { 16 }, 0, 0));
+    let biomes = Array2d::new_init(((32, -48), (47, -33)), |_| BiomeId::Hills);
+    let functors = vec![(
+      (40, 10, -40),
+      SavedFunctor::InternalInventory(InternalInventory::with_capacity(3)),
+    )];
+    let blobs = [
+      (1, bincode::serialize(&(&grid, &light_map)).unwrap()),
+      (2, bincode::serialize(&((&grid, &light_map), &functors)).unwrap()),
+      (
+        3,
+        bincode::serialize(&((&grid, &light_map, &biomes), &functors)).unwrap(),
+      ),
+    ];
+    for (version, data) in blobs {
+      let saved = decode_chunk(version, &data).unwrap();
+      let chunk = saved.chunk;
+      assert_eq!(chunk.coord, (2, -3));
+      // Everything above the cobble is air under open sky and gets compacted away
+      assert_eq!(chunk.sections.len(), 8);
+      assert!(chunk.sections[..2].iter().all(Option::is_some));
+      assert!(chunk.sections[2..].iter().all(Option::is_none));
+      grid.foreach(|c, block| {
+        assert_eq!(chunk.get(c).unwrap().block, block.block);
+        assert_eq!(chunk.get_light_level(c).unwrap().heaven, light_map[c].heaven);
+      });
+      // Biomes came along in version 3, functors in version 2
+      let biome = if version < 3 {
+        BiomeId::default()
+      } else {
+        BiomeId::Hills
+      };
+      assert_eq!(chunk.biomes[(40, -40)], biome);
+      assert_eq!(saved.functors.len(), if version < 2 { 0 } else { 1 });
+    }
+    assert!(decode_chunk(CHUNK_FORMAT_VERSION + 1, &[]).is_err());
+  }
+
+  fn generate(seed: u64, chunk_coord: DD) -> (Vec<u8>, Vec<(DDD, BlockId)>) {
+    let registry = BlockRegistry::default();
+    let ores = default_ores().iter().map(|ore| ore.resolve(&registry).unwrap()).collect();
+    let generator = PerlinGenerator::new(seed, DEFAULT_WORLD_HEIGHT, ores);
+    let (saved, spilled) = SavedChunk::generate(chunk_coord, &generator, &registry);
+    (encode_chunk(&saved.chunk, saved.functors), spilled)
+  }
+
+  #[test]
+  fn same_seed_generates_same_chunk() {
+    for chunk_coord in [(0, 0), (3, -5), (-17, 12)] {
+      assert_eq!(generate(42, chunk_coord), generate(42, chunk_coord));
+    }
+  }
+
+  #[test]
+  fn other_seed_generates_other_chunk() {
+    assert_ne!(generate(42, (3, -5)).0, generate(43, (3, -5)).0);
+  }
+}
@@ -0,0 +1,66 @@
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
+use shikataganai_common::networking::FunctorType;
+use shikataganai_common::util::array::DDD;
+
+// What the subscribers got sent last, only what differs from it goes out to them
+pub enum SentFunctor {
+  InternalInventory(InternalInventory),
+  Furnace(Furnace),
+}
+
+pub struct Subscription {
+  // Block entity the clients subscribed to, a different one at the location means the block got replaced
+  pub entity: Entity,
+  pub clients: HashSet<u64>,
+  // Clients that just subscribed and still need the whole functor
+  pub fresh: HashSet<u64>,
+  pub sent: Option<SentFunctor>,
+}
+
+// Clients that have the interface of a block entity open, they get its functors pushed to them whenever they change
+#[derive(Default, Resource)]
+pub struct FunctorSubscriptions {
+  pub subscribers: HashMap<(DDD, FunctorType), Subscription>,
+}
+
+impl FunctorSubscriptions {
+  pub fn subscribe(&mut self, location: DDD, functor_type: FunctorType, entity: Entity, client: u64) {
+    let subscription = self
+      .subscribers
+      .entry((location, functor_type))
+      .or_insert_with(|| Subscription {
+        entity,
+        clients: HashSet::default(),
+        fresh: HashSet::default(),
+        sent: None,
+      });
+    if subscription.entity != entity {
+      subscription.entity = entity;
+      subscription.clients.clear();
+      subscription.fresh.clear();
+      subscription.sent = None;
+    }
+    subscription.clients.insert(client);
+    subscription.fresh.insert(client);
+  }
+
+  pub fn unsubscribe(&mut self, location: DDD, client: u64) {
+    self.drop_where(|(subscribed_location, _), subscribed_client| {
+      *subscribed_location == location && subscribed_client == client
+    });
+  }
+
+  pub fn drop_client(&mut self, client: u64) {
+    self.drop_where(|_, subscribed_client| subscribed_client == client);
+  }
+
+  fn drop_where(&mut self, condition: impl Fn(&(DDD, FunctorType), u64) -> bool) {
+    self.subscribers.retain(|key, subscription| {
+      subscription.clients.retain(|client| !condition(key, *client));
+      subscription.fresh.retain(|client| !condition(key, *client));
+      !subscription.clients.is_empty()
+    });
+  }
+}
@@ -0,0 +1,59 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::util::array::{DD, DDD};
+
+// Blocks that asked to be ticked again later. They only live as long as the server runs, collect_unloaded_chunks drops
+// those of a chunk along with it.
+#[derive(Default, Resource)]
+pub struct ScheduledTicks {
+  // Server tick every location is due at
+  pub due: HashMap<DDD, u32>,
+}
+
+impl ScheduledTicks {
+  // A location is ticked once at most, the earlier of two schedules wins
+  pub fn schedule(&mut self, location: DDD, now: u32, delay: u32) {
+    let due = now + delay.max(1);
+    self
+      .due
+      .entry(location)
+      .and_modify(|tick| *tick = (*tick).min(due))
+      .or_insert(due);
+  }
+
+  pub fn drop_chunk(&mut self, chunk_coord: DD) {
+    self
+      .due
+      .retain(|location, _| GameWorld::get_chunk_coord(*location) != chunk_coord);
+  }
+
+  pub fn take_due(&mut self, now: u32) -> Vec<DDD> {
+    let mut locations: Vec<(u32, DDD)> = self
+      .due
+      .iter()
+      .filter(|(_, tick)| **tick <= now)
+      .map(|(location, tick)| (*tick, *location))
+      .collect();
+    locations.sort();
+    for (_, location) in locations.iter() {
+      self.due.remove(location);
+    }
+    locations.into_iter().map(|(_, location)| location).collect()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn unloaded_chunks_lose_their_ticks() {
+    let mut ticks = ScheduledTicks::default();
+    ticks.schedule((3, 10, 3), 0, 5);
+    ticks.schedule((-1, 10, 3), 0, 5);
+    ticks.schedule((17, 10, 3), 0, 5);
+    ticks.drop_chunk((0, 0));
+    assert_eq!(ticks.take_due(5), vec![(-1, 10, 3), (17, 10, 3)]);
+  }
+}
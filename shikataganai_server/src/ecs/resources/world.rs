@@ -1,41 +1,128 @@
-use crate::ecs::systems::chunkgen::ChunkTask;
+use crate::ecs::plugins::settings::Generator;
+use crate::ecs::resources::region::{RegionStorage, SavedChunk};
+use crate::ecs::systems::chunkgen::{ChunkSource, ChunkTask};
 use bevy::prelude::*;
 use bevy::tasks::AsyncComputeTaskPool;
+use bevy::utils::{HashMap, HashSet};
 use bevy_renet::renet::RenetServer;
 use flate2::write::ZlibEncoder;
 use flate2::Compression;
+use serde::Serialize;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::components::chunk::Chunk;
-use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::ecs::resources::world::{ChunkState, GameWorld};
 use shikataganai_common::networking::{ServerChannel, ServerMessage, RELIABLE_CHANNEL_MAX_LENGTH};
-use shikataganai_common::util::array::DD;
+use shikataganai_common::util::array::{DD, DDD};
 use std::io::Write;
 
+// Chunks that changed since they were last written to disk
+#[derive(Default, Resource)]
+pub struct DirtyChunks {
+  pub chunks: HashSet<DD>,
+}
+
+impl DirtyChunks {
+  pub fn mark(&mut self, location: DDD) {
+    self.chunks.insert(GameWorld::get_chunk_coord(location));
+  }
+}
+
+#[derive(Default, Resource)]
+pub struct ChunkWatchers {
+  // Chunks every client has been sent and still holds a copy of
+  pub sent: HashMap<u64, HashSet<DD>>,
+  // Clients within view distance of a loaded chunk that hold a copy of it
+  pub watchers: HashMap<DD, HashSet<u64>>,
+  // When a loaded chunk lost its last watcher
+  pub unwatched_since: HashMap<DD, f64>,
+}
+
 pub trait ServerGameWorld {
-  fn get_chunk_or_spawn(&mut self, chunk_coord: DD, commands: &mut Commands, client: u64) -> Option<&Chunk>;
+  // Queues the client for the chunk data if it isn't loaded yet
+  fn get_chunk_or_spawn(
+    &mut self,
+    chunk_coord: DD,
+    commands: &mut Commands,
+    client: Option<u64>,
+    storage: &RegionStorage,
+    generator: &Generator,
+    registry: &BlockRegistry,
+  ) -> Option<&Chunk>;
 }
 
 impl ServerGameWorld for GameWorld {
-  fn get_chunk_or_spawn(&mut self, chunk_coord: DD, commands: &mut Commands, client: u64) -> Option<&Chunk> {
-    match self.chunks.get(&chunk_coord) {
+  fn get_chunk_or_spawn(
+    &mut self,
+    chunk_coord: DD,
+    commands: &mut Commands,
+    client: Option<u64>,
+    storage: &RegionStorage,
+    generator: &Generator,
+    registry: &BlockRegistry,
+  ) -> Option<&Chunk> {
+    match self.states.get_mut(&chunk_coord) {
       None => {
-        if !self.generating.contains(&chunk_coord) {
-          self.generating.push(chunk_coord);
-          let dispatcher = AsyncComputeTaskPool::get();
-          commands.spawn(ChunkTask {
-            task: dispatcher.spawn(Chunk::generate(chunk_coord)),
-            coord: chunk_coord,
-            client,
-          });
+        self.states.insert(
+          chunk_coord,
+          ChunkState::Loading {
+            waiters: client.into_iter().collect(),
+          },
+        );
+        spawn_chunk_task(commands, chunk_coord, storage, generator, registry, ChunkSource::Disk);
+        None
+      }
+      Some(ChunkState::Loading { waiters }) | Some(ChunkState::Generating { waiters }) => {
+        if let Some(client) = client && !waiters.contains(&client) {
+          waiters.push(client);
         }
         None
       }
-      Some(chunk) => Some(chunk),
+      Some(state) => {
+        // Still in memory, the pending unload leaves it alone once it sees it's loaded again.
+        *state = ChunkState::Loaded;
+        self.chunks.get(&chunk_coord)
+      }
     }
   }
 }
 
-pub fn send_chunk_data(server: &mut RenetServer, chunk: &Chunk, client: u64) {
-  let data = bincode::serialize(&chunk).unwrap();
+pub fn spawn_chunk_task(
+  commands: &mut Commands,
+  chunk_coord: DD,
+  storage: &RegionStorage,
+  generator: &Generator,
+  registry: &BlockRegistry,
+  source: ChunkSource,
+) {
+  let dispatcher = AsyncComputeTaskPool::get();
+  let task = match source {
+    ChunkSource::Disk => {
+      let storage = storage.clone();
+      dispatcher.spawn(async move {
+        storage
+          .load_chunk(chunk_coord)
+          .unwrap_or_else(|err| {
+            println!("Failed to load chunk {:?}, regenerating: {}", chunk_coord, err);
+            None
+          })
+          .map(|saved_chunk| (saved_chunk, vec![]))
+      })
+    }
+    ChunkSource::Generated => {
+      let generator = generator.clone();
+      let registry = registry.clone();
+      dispatcher.spawn(async move { Some(SavedChunk::generate(chunk_coord, generator.0.as_ref(), &registry)) })
+    }
+  };
+  commands.spawn(ChunkTask {
+    task,
+    coord: chunk_coord,
+    source,
+  });
+}
+
+fn compress<T: Serialize>(value: &T) -> Vec<u8> {
+  let data = bincode::serialize(value).unwrap();
   let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
   encoder.write_all(&data).unwrap();
   let message = encoder.finish().unwrap();
@@ -43,9 +130,31 @@ pub fn send_chunk_data(server: &mut RenetServer, chunk: &Chunk, client: u64) {
     message.len() <= RELIABLE_CHANNEL_MAX_LENGTH as usize,
     "Chunk packet size limit reached. Stopgap has been used up. Good luck fixing that."
   );
+  message
+}
+
+// Missing sections are left out, the client takes them for air
+pub fn send_chunk_data(server: &mut RenetServer, chunk: &Chunk, client: u64) {
   server.send_message(
     client,
     ServerChannel::GameEvent.id(),
-    bincode::serialize(&ServerMessage::ChunkData { chunk: message }).unwrap(),
+    bincode::serialize(&ServerMessage::ChunkData {
+      chunk: compress(&chunk.skeleton()),
+    })
+    .unwrap(),
   );
+  for (index, section) in chunk.sections.iter().enumerate() {
+    if let Some(section) = section {
+      server.send_message(
+        client,
+        ServerChannel::GameEvent.id(),
+        bincode::serialize(&ServerMessage::SectionData {
+          chunk_coord: chunk.coord,
+          section: index as u32,
+          data: compress(section),
+        })
+        .unwrap(),
+      );
+    }
+  }
 }
@@ -1,16 +1,35 @@
-use crate::ecs::resources::world::send_chunk_data;
+use crate::ecs::plugins::settings::Generator;
+use crate::ecs::resources::pending::PendingBlocks;
+use crate::ecs::resources::region::{RegionStorage, SavedChunk};
+use crate::ecs::resources::world::{send_chunk_data, spawn_chunk_task, DirtyChunks};
+use crate::ecs::systems::ticks::{notify_neighbours, BlockUpdateEvent};
 use bevy::prelude::*;
 use bevy::tasks::Task;
 use bevy_renet::renet::RenetServer;
-use shikataganai_common::ecs::components::chunk::Chunk;
-use shikataganai_common::ecs::resources::world::GameWorld;
-use shikataganai_common::util::array::DD;
+use bincode::serialize;
+use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::{BlockMeta, ReverseLocation};
+use shikataganai_common::ecs::resources::light::RelightEvent;
+use shikataganai_common::ecs::resources::world::{ChunkState, GameWorld};
+use shikataganai_common::networking::{BlockTransfer, ServerChannel, ServerMessage};
+use shikataganai_common::util::array::{DD, DDD};
+use shikataganai_common::worldgen::place_decorations;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ChunkSource {
+  Disk,
+  Generated,
+}
+
+// None when the chunk was never saved, the blocks are decorations that spilled over into other chunks
+pub type LoadedChunk = Option<(SavedChunk, Vec<(DDD, BlockId)>)>;
 
 #[derive(Component)]
 pub struct ChunkTask {
-  pub task: Task<Chunk>,
+  pub task: Task<LoadedChunk>,
   pub coord: DD,
-  pub client: u64,
+  pub source: ChunkSource,
 }
 
 pub fn collect_async_chunks(
@@ -18,13 +37,96 @@ pub fn collect_async_chunks(
   mut commands: Commands,
   mut server: ResMut<RenetServer>,
   mut world: ResMut<GameWorld>,
+  (mut relight, mut block_updates): (EventWriter<RelightEvent>, EventWriter<BlockUpdateEvent>),
+  (mut dirty_chunks, mut pending_blocks): (ResMut<DirtyChunks>, ResMut<PendingBlocks>),
+  (storage, generator, registry): (Res<RegionStorage>, Res<Generator>, Res<BlockRegistry>),
 ) {
   for (e, mut task) in query.iter_mut() {
-    if let Some(chunk) = futures_lite::future::block_on(futures_lite::future::poll_once(&mut task.task)) {
-      send_chunk_data(server.as_mut(), &chunk, task.client);
-      world.chunks.insert(task.coord, chunk);
-      world.remove_from_generating(task.coord);
-      commands.entity(e).remove::<ChunkTask>();
+    if let Some(saved_chunk) = futures_lite::future::block_on(futures_lite::future::poll_once(&mut task.task)) {
+      commands.entity(e).despawn();
+      match saved_chunk {
+        None => {
+          if let Some(ChunkState::Loading { waiters }) = world.states.remove(&task.coord) {
+            world.states.insert(task.coord, ChunkState::Generating { waiters });
+          }
+          spawn_chunk_task(
+            &mut commands,
+            task.coord,
+            storage.as_ref(),
+            generator.as_ref(),
+            registry.as_ref(),
+            ChunkSource::Generated,
+          );
+        }
+        Some((SavedChunk { mut chunk, functors }, spilled)) => {
+          // A block entity with several functors has one entry for each
+          for (location, functor) in functors {
+            let block = chunk.get_mut(location).unwrap();
+            let mut entity_commands = if block.entity == Entity::from_bits(0) {
+              commands.spawn(ReverseLocation(location))
+            } else {
+              commands.entity(block.entity)
+            };
+            functor.insert(&mut entity_commands);
+            block.entity = entity_commands.id();
+          }
+          // Blocks saved before their definition had functors, e.g. furnaces of older worlds, start out with fresh ones
+          if task.source == ChunkSource::Disk {
+            let mut missing = vec![];
+            chunk.foreach(|location, block| {
+              if block.entity == Entity::from_bits(0) && registry.get(block.block).need_to_spawn_functors() {
+                missing.push(location);
+              }
+            });
+            for location in missing {
+              let block = chunk.get_mut(location).unwrap();
+              registry.get(block.block).spawn_or_add_functors(block, location, &mut commands);
+            }
+          }
+          if task.source == ChunkSource::Generated {
+            dirty_chunks.chunks.insert(task.coord);
+          }
+          let mut placed = vec![];
+          if let Some(blocks) = pending_blocks.blocks.remove(&task.coord) {
+            (placed, _) = place_decorations(&mut chunk, blocks);
+            if !placed.is_empty() {
+              chunk.relight_sky(registry.as_ref());
+              dirty_chunks.chunks.insert(task.coord);
+            }
+          }
+          for client in world.insert_chunk(task.coord, chunk) {
+            send_chunk_data(server.as_mut(), &world.chunks[&task.coord], client);
+          }
+          for location in placed {
+            notify_neighbours(&mut block_updates, location);
+          }
+          for (location, block) in spilled {
+            let chunk_coord = GameWorld::get_chunk_coord(location);
+            match world.chunk_state(chunk_coord) {
+              Some(ChunkState::Loaded) | Some(ChunkState::Unloading) => {
+                if let Some(target) = world.get_mut(location) && target.block == BlockId::AIR {
+                  *target = block.into();
+                  dirty_chunks.mark(location);
+                  relight.send(RelightEvent::Relight(location));
+                  notify_neighbours(&mut block_updates, location);
+                  server.broadcast_message(
+                    ServerChannel::GameEvent.id(),
+                    serialize(&ServerMessage::BlockPlace {
+                      location,
+                      block_transfer: BlockTransfer {
+                        block,
+                        meta: BlockMeta { v: 0 },
+                      },
+                    })
+                    .unwrap(),
+                  );
+                }
+              }
+              _ => pending_blocks.blocks.entry(chunk_coord).or_default().push((location, block)),
+            }
+          }
+        }
+      }
     }
   }
 }
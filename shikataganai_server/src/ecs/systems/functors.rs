@@ -0,0 +1,110 @@
+use crate::ecs::resources::subscriptions::{FunctorSubscriptions, SentFunctor};
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use bincode::serialize;
+use serde::Serialize;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::networking::{FunctorType, InventoryRef, ServerChannel, ServerMessage};
+use shikataganai_common::util::array::DDD;
+
+// A burning furnace changes on every tick, its bars only go out to the clients in this many steps
+const FURNACE_STEPS: u32 = 20;
+
+fn furnace_steps(furnace: &Furnace) -> (u32, u32, u32) {
+  let step = |value: u32, total: u32| (value * FURNACE_STEPS).checked_div(total).unwrap_or(0);
+  (
+    furnace.burn_total,
+    step(furnace.burn, furnace.burn_total),
+    step(furnace.progress, Furnace::COOK_TIME),
+  )
+}
+
+fn functor_message(location: DDD, functor_type: FunctorType, functor: &impl Serialize) -> ServerMessage {
+  ServerMessage::Functor {
+    location,
+    functor_type,
+    functor: serialize(functor).unwrap(),
+  }
+}
+
+fn send_to<'a>(server: &mut RenetServer, clients: impl IntoIterator<Item = &'a u64>, message: &ServerMessage) {
+  let message = serialize(message).unwrap();
+  for client in clients {
+    server.send_message(*client, ServerChannel::GameEvent.id(), message.clone());
+  }
+}
+
+// New subscribers get the whole functor, the others only what changed since it was last sent
+pub fn push_functor_updates(
+  mut server: ResMut<RenetServer>,
+  mut subscriptions: ResMut<FunctorSubscriptions>,
+  game_world: Res<GameWorld>,
+  inventories: Query<(&InternalInventory, ChangeTrackers<InternalInventory>)>,
+  furnaces: Query<(&Furnace, ChangeTrackers<Furnace>)>,
+) {
+  // Removed or replaced blocks and the ones that went away with their chunk take their subscriptions with them
+  subscriptions.subscribers.retain(|(location, _), subscription| {
+    game_world
+      .get(*location)
+      .is_some_and(|block| block.entity == subscription.entity)
+  });
+  for ((location, functor_type), subscription) in subscriptions.subscribers.iter_mut() {
+    match functor_type {
+      FunctorType::InternalInventory => {
+        let (inventory, changes) = match inventories.get(subscription.entity) {
+          Err(_) => continue,
+          Ok(found) => found,
+        };
+        if !changes.is_changed() && subscription.fresh.is_empty() {
+          continue;
+        }
+        let fresh = std::mem::take(&mut subscription.fresh);
+        if let Some(SentFunctor::InternalInventory(sent)) = &subscription.sent {
+          let slots = (0..inventory.inventory.len())
+            .filter(|slot| sent.inventory.get(*slot) != inventory.inventory.get(*slot))
+            .collect::<Vec<_>>();
+          if !slots.is_empty() {
+            send_to(
+              server.as_mut(),
+              subscription.clients.difference(&fresh),
+              &ServerMessage::InventorySlots {
+                inventory: InventoryRef::Block(*location),
+                slots: inventory.slots(slots),
+              },
+            );
+          }
+        }
+        send_to(
+          server.as_mut(),
+          fresh.iter(),
+          &functor_message(*location, *functor_type, inventory),
+        );
+        subscription.sent = Some(SentFunctor::InternalInventory(inventory.clone()));
+      }
+      FunctorType::Furnace => {
+        let (furnace, changes) = match furnaces.get(subscription.entity) {
+          Err(_) => continue,
+          Ok(found) => found,
+        };
+        if !changes.is_changed() && subscription.fresh.is_empty() {
+          continue;
+        }
+        let fresh = std::mem::take(&mut subscription.fresh);
+        let moved = match &subscription.sent {
+          Some(SentFunctor::Furnace(sent)) => furnace_steps(sent) != furnace_steps(furnace),
+          _ => true,
+        };
+        let clients = if moved { &subscription.clients } else { &fresh };
+        send_to(
+          server.as_mut(),
+          clients.iter(),
+          &functor_message(*location, *functor_type, furnace),
+        );
+        if moved {
+          subscription.sent = Some(SentFunctor::Furnace(furnace.clone()));
+        }
+      }
+    }
+  }
+}
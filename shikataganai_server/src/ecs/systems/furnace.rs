@@ -0,0 +1,27 @@
+use crate::ecs::resources::world::DirtyChunks;
+use bevy::prelude::*;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::ReverseLocation;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory};
+
+// Every furnace of the loaded chunks, whether someone has it open or not
+pub fn smelt_furnaces(
+  mut dirty_chunks: ResMut<DirtyChunks>,
+  registry: Res<BlockRegistry>,
+  mut furnaces: Query<(&mut Furnace, &mut InternalInventory, &ReverseLocation)>,
+) {
+  for (mut furnace, mut inventory, location) in furnaces.iter_mut() {
+    let mut state = furnace.clone();
+    let mut slots = inventory.clone();
+    state.tick(&mut slots, registry.as_ref());
+    // Only write back what changed, subscribers get sent whatever is marked changed
+    if state != *furnace {
+      *furnace = state;
+      dirty_chunks.mark(location.0);
+    }
+    if slots != *inventory {
+      *inventory = slots;
+      dirty_chunks.mark(location.0);
+    }
+  }
+}
@@ -0,0 +1,208 @@
+use crate::ecs::plugins::server::{ClientId, PlayerEntities};
+use crate::ecs::resources::players::SpawnPoint;
+use crate::ecs::resources::world::DirtyChunks;
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use bincode::serialize;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::QuantifiedBlockOrItem;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory, SlotTransaction};
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::networking::{InventoryRef, InventorySlot, ServerChannel, ServerMessage};
+use shikataganai_common::util::array::DDD;
+
+// Block inventories further away from the player than this can't be reached, a bit more than the client's selection
+const INTERACTION_DISTANCE: f32 = 12.0;
+
+fn block_center(location: DDD) -> Vec3 {
+  Vec3::new(location.0 as f32, location.1 as f32, location.2 as f32) + Vec3::splat(0.5)
+}
+
+pub fn within_reach(translation: Vec3, location: DDD) -> bool {
+  translation.distance(block_center(location)) <= INTERACTION_DISTANCE
+}
+
+// Tells the player what these slots of their own inventory hold now
+pub fn send_player_slots(
+  server: &mut RenetServer,
+  client: u64,
+  inventory: &InternalInventory,
+  slots: impl IntoIterator<Item = usize>,
+) {
+  server.send_message(
+    client,
+    ServerChannel::GameEvent.id(),
+    serialize(&ServerMessage::InventorySlots {
+      inventory: InventoryRef::Player,
+      slots: inventory.slots(slots),
+    })
+    .unwrap(),
+  );
+}
+
+// Puts drops, crafting results and the like into the player's inventory. Either all of them fit or nothing is given
+// and it returns false, whatever they came from should stay as it is then.
+pub fn give_player(
+  server: &mut RenetServer,
+  client: u64,
+  inventory: &mut InternalInventory,
+  stacks: Vec<QuantifiedBlockOrItem>,
+) -> bool {
+  if !inventory.fits(&stacks) {
+    return false;
+  }
+  let mut slots = vec![];
+  for stack in stacks {
+    let slot = inventory.add(stack.block_or_item, stack.quant).unwrap();
+    if !slots.contains(&slot) {
+      slots.push(slot);
+    }
+  }
+  if !slots.is_empty() {
+    send_player_slots(server, client, inventory, slots);
+  }
+  true
+}
+
+// There are no item entities yet, so what a block leaves behind when it breaks off on its own goes to the closest player
+// in reach with room for all of it. False when there's nobody like that, the block should stay until there is.
+pub fn give_nearest_player(
+  server: &mut RenetServer,
+  location: DDD,
+  players: &mut Query<(&ClientId, &Transform, &mut InternalInventory), With<SpawnPoint>>,
+  stacks: Vec<QuantifiedBlockOrItem>,
+) -> bool {
+  if stacks.is_empty() {
+    return true;
+  }
+  let distance = |transform: &Transform| transform.translation.distance_squared(block_center(location));
+  let nearest = players
+    .iter_mut()
+    .filter(|(_, transform, inventory)| within_reach(transform.translation, location) && inventory.fits(&stacks))
+    .min_by(|(_, a, _), (_, b, _)| distance(a).total_cmp(&distance(b)));
+  match nearest {
+    None => false,
+    Some((client, _, mut inventory)) => give_player(server, client.0, inventory.as_mut(), stacks),
+  }
+}
+
+#[derive(Debug)]
+pub struct InventoryTransactionEvent {
+  pub client: u64,
+  pub from: InventorySlot,
+  pub to: InventorySlot,
+  pub transaction: SlotTransaction,
+}
+
+fn take_slot(
+  inventories: &mut Query<&mut InternalInventory>,
+  (entity, slot): (Entity, usize),
+) -> Option<Option<QuantifiedBlockOrItem>> {
+  inventories
+    .get_mut(entity)
+    .ok()
+    .and_then(|mut inventory| inventory.inventory.get_mut(slot).map(|stack| stack.take()))
+}
+
+fn put_slot(
+  inventories: &mut Query<&mut InternalInventory>,
+  (entity, slot): (Entity, usize),
+  stack: Option<QuantifiedBlockOrItem>,
+) {
+  inventories.get_mut(entity).unwrap().inventory[slot] = stack;
+}
+
+fn transact(
+  inventories: &mut Query<&mut InternalInventory>,
+  from: (Entity, usize),
+  to: (Entity, usize),
+  transaction: SlotTransaction,
+  accepts: impl Fn((Entity, usize), &Option<QuantifiedBlockOrItem>) -> bool,
+) -> bool {
+  let mut from_stack = match take_slot(inventories, from) {
+    None => return false,
+    Some(stack) => stack,
+  };
+  let mut to_stack = match take_slot(inventories, to) {
+    None => {
+      put_slot(inventories, from, from_stack);
+      return false;
+    }
+    Some(stack) => stack,
+  };
+  let original = (from_stack.clone(), to_stack.clone());
+  let mut applied = transaction.apply(&mut from_stack, &mut to_stack);
+  // Only a move puts something new into the slot it takes from, the others just take some out of it
+  applied =
+    applied && accepts(to, &to_stack) && (!matches!(transaction, SlotTransaction::Move) || accepts(from, &from_stack));
+  if !applied {
+    (from_stack, to_stack) = original;
+  }
+  put_slot(inventories, from, from_stack);
+  put_slot(inventories, to, to_stack);
+  applied
+}
+
+pub fn handle_inventory_transactions(
+  mut transaction_events: EventReader<InventoryTransactionEvent>,
+  mut server: ResMut<RenetServer>,
+  mut dirty_chunks: ResMut<DirtyChunks>,
+  (game_world, player_entities, registry): (Res<GameWorld>, Res<PlayerEntities>, Res<BlockRegistry>),
+  transforms: Query<&Transform>,
+  mut inventories: Query<&mut InternalInventory>,
+  furnaces: Query<&Furnace>,
+) {
+  // Furnace slots only take what can go in there, every other slot takes anything
+  let accepts = |(entity, slot): (Entity, usize), stack: &Option<QuantifiedBlockOrItem>| {
+    furnaces.get(entity).is_err()
+      || stack
+        .as_ref()
+        .is_none_or(|stack| Furnace::accepts(slot, stack.block_or_item, registry.as_ref()))
+  };
+  for event in transaction_events.iter() {
+    let player = match player_entities.players.get(&event.client) {
+      None => continue,
+      Some(player) => *player,
+    };
+    let translation = transforms.get(player).unwrap().translation;
+    let entity = |inventory: InventoryRef| match inventory {
+      InventoryRef::Player => Some(player),
+      InventoryRef::Block(location) => game_world
+        .get(location)
+        .map(|block| block.entity)
+        .filter(|entity| *entity != Entity::from_bits(0) && within_reach(translation, location)),
+    };
+    let (from, to) = (entity(event.from.inventory), entity(event.to.inventory));
+    let applied = match (from, to) {
+      (Some(from), Some(to)) if event.from != event.to => transact(
+        &mut inventories,
+        (from, event.from.slot),
+        (to, event.to.slot),
+        event.transaction,
+        accepts,
+      ),
+      _ => false,
+    };
+    if applied {
+      for slot in [event.from, event.to] {
+        if let InventoryRef::Block(location) = slot.inventory {
+          dirty_chunks.mark(location);
+        }
+      }
+    }
+    // Either way the client gets told what's really in there, its own guess may be off
+    for (slot, entity) in [(event.from, from), (event.to, to)] {
+      if let Some(Ok(inventory)) = entity.map(|entity| inventories.get(entity)) {
+        server.send_message(
+          event.client,
+          ServerChannel::GameEvent.id(),
+          serialize(&ServerMessage::InventorySlots {
+            inventory: slot.inventory,
+            slots: inventory.slots([slot.slot]),
+          })
+          .unwrap(),
+        );
+      }
+    }
+  }
+}
@@ -1,24 +1,33 @@
+use crate::ecs::resources::world::DirtyChunks;
 use bevy::prelude::*;
 use bevy_renet::renet::RenetServer;
 use bincode::serialize;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
 use shikataganai_common::ecs::resources::light::{relight_helper, RelightEvent};
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::networking::{ServerChannel, ServerMessage};
 
+// A relight takes 15 bytes
+const RELIGHTS_PER_MESSAGE: usize = 600;
+
 pub fn relight_system(
   mut relight: EventReader<RelightEvent>,
   mut game_world: ResMut<GameWorld>,
   mut server: ResMut<RenetServer>,
+  mut dirty_chunks: ResMut<DirtyChunks>,
+  registry: Res<BlockRegistry>,
 ) {
   let mut relights = vec![];
-  for coord in relight_helper(&mut relight, game_world.as_mut()).iter() {
-    relights.push((*coord, game_world.get_light_level(*coord).unwrap()))
+  for coord in relight_helper(&mut relight, game_world.as_mut(), registry.as_ref()).iter() {
+    relights.push((*coord, game_world.get_light_level(*coord).unwrap()));
+    dirty_chunks.mark(*coord);
   }
-  if !relights.is_empty() {
-    let message = serialize(&ServerMessage::Relight { relights }).unwrap();
-    // TODO: idk
-    if message.len() < 2000 {
-      server.broadcast_message(ServerChannel::GameEvent.id(), message)
-    }
+  // Light sources change a lot of blocks at once, split them so every message fits the channel
+  for relights in relights.chunks(RELIGHTS_PER_MESSAGE) {
+    let message = serialize(&ServerMessage::Relight {
+      relights: relights.to_vec(),
+    })
+    .unwrap();
+    server.broadcast_message(ServerChannel::GameEvent.id(), message)
   }
 }
@@ -1,2 +1,9 @@
 pub mod chunkgen;
+pub mod furnace;
+pub mod functors;
+pub mod inventory;
 pub mod light;
+pub mod persistence;
+pub mod shutdown;
+pub mod ticks;
+pub mod unload;
@@ -0,0 +1,88 @@
+use crate::ecs::plugins::settings::AutosaveInterval;
+use crate::ecs::resources::pending::PendingBlocks;
+use crate::ecs::resources::players::{PlayerRecord, PlayerStorage, SpawnPoint};
+use crate::ecs::resources::region::RegionStorage;
+use crate::ecs::resources::world::DirtyChunks;
+use bevy::prelude::*;
+use shikataganai_common::ecs::components::chunk::Chunk;
+use shikataganai_common::ecs::components::functors::{Furnace, InternalInventory, SavedFunctor};
+use shikataganai_common::ecs::resources::player::PlayerNickname;
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::networking::PolarRotation;
+use shikataganai_common::util::array::DDD;
+
+// Writes every changed chunk and every online player to disk
+pub struct SaveAllEvent;
+
+// Every functor a block entity can have, they get saved together with the chunk
+pub type FunctorQuery<'w, 's> = Query<'w, 's, (Option<&'static InternalInventory>, Option<&'static Furnace>)>;
+
+pub fn chunk_functors(chunk: &Chunk, functor_query: &FunctorQuery) -> Vec<(DDD, SavedFunctor)> {
+  let mut functors = vec![];
+  chunk.foreach(|location, block| {
+    if block.entity == Entity::from_bits(0) {
+      return;
+    }
+    if let Ok((internal_inventory, furnace)) = functor_query.get(block.entity) {
+      if let Some(internal_inventory) = internal_inventory {
+        functors.push((location, SavedFunctor::InternalInventory(internal_inventory.clone())));
+      }
+      if let Some(furnace) = furnace {
+        functors.push((location, SavedFunctor::Furnace(furnace.clone())));
+      }
+    }
+  });
+  functors
+}
+
+pub fn autosave(
+  time: Res<Time>,
+  autosave_interval: Res<AutosaveInterval>,
+  mut last_save: Local<f64>,
+  mut save_all: EventWriter<SaveAllEvent>,
+) {
+  if time.elapsed_seconds_f64() - *last_save < autosave_interval.0.as_secs_f64() {
+    return;
+  }
+  *last_save = time.elapsed_seconds_f64();
+  save_all.send(SaveAllEvent);
+}
+
+pub fn save_world(
+  mut save_all: EventReader<SaveAllEvent>,
+  game_world: Res<GameWorld>,
+  mut dirty_chunks: ResMut<DirtyChunks>,
+  (storage, player_storage, pending_blocks): (Res<RegionStorage>, Res<PlayerStorage>, Res<PendingBlocks>),
+  functor_query: FunctorQuery,
+  player_query: Query<(
+    &PlayerNickname,
+    &Transform,
+    &PolarRotation,
+    &SpawnPoint,
+    &InternalInventory,
+  )>,
+) {
+  if save_all.iter().last().is_none() {
+    return;
+  }
+  let chunks = dirty_chunks.chunks.drain().filter_map(|chunk_coord| {
+    game_world
+      .chunks
+      .get(&chunk_coord)
+      .map(|chunk| (chunk_coord, chunk, chunk_functors(chunk, &functor_query)))
+  });
+  if let Err(err) = storage.save_chunks(chunks) {
+    println!("Failed to save chunks: {}", err);
+  }
+  if let Err(err) = pending_blocks.save(storage.root()) {
+    println!("Failed to save pending blocks: {}", err);
+  }
+  for (nickname, transform, rotation, spawn_point, inventory) in player_query.iter() {
+    if let Err(err) = player_storage.save(
+      &nickname.0,
+      &PlayerRecord::new(transform, rotation, spawn_point, inventory),
+    ) {
+      println!("Failed to save player {}: {}", nickname.0, err);
+    }
+  }
+}
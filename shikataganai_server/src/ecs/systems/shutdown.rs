@@ -0,0 +1,41 @@
+use crate::ecs::resources::control::ServerControl;
+use crate::ecs::systems::persistence::SaveAllEvent;
+use crate::ecs::systems::unload::UnloadTask;
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use bincode::serialize;
+use shikataganai_common::networking::{ServerChannel, ServerMessage};
+
+pub fn server_control(
+  control: Res<ServerControl>,
+  mut server: ResMut<RenetServer>,
+  mut save_all: EventWriter<SaveAllEvent>,
+  mut app_exit: EventWriter<AppExit>,
+  mut shutting_down: Local<bool>,
+  unload_tasks: Query<(), With<UnloadTask>>,
+) {
+  // Give the shutdown notice a frame to get sent out before dropping everyone.
+  if *shutting_down {
+    // Chunks that are being unloaded only exist in their in-flight writes.
+    if !unload_tasks.is_empty() {
+      return;
+    }
+    server.disconnect_clients();
+    app_exit.send(AppExit);
+    return;
+  }
+  if control.take_save_all() {
+    println!("Saving the world");
+    save_all.send(SaveAllEvent);
+  }
+  if control.take_shutdown() {
+    println!("Shutting down");
+    server.broadcast_message(
+      ServerChannel::GameEvent.id(),
+      serialize(&ServerMessage::ServerShutdown).unwrap(),
+    );
+    save_all.send(SaveAllEvent);
+    *shutting_down = true;
+  }
+}
@@ -0,0 +1,208 @@
+use crate::ecs::plugins::server::{ClientId, ServerTick};
+use crate::ecs::plugins::settings::RandomTickSpeed;
+use crate::ecs::resources::players::SpawnPoint;
+use crate::ecs::resources::ticks::ScheduledTicks;
+use crate::ecs::resources::world::DirtyChunks;
+use crate::ecs::systems::inventory::give_nearest_player;
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use bincode::serialize;
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::components::blocks::tick::{TickKind, TickOutcome};
+use shikataganai_common::ecs::components::chunk::{section_bounds, SECTION_HEIGHT};
+use shikataganai_common::ecs::components::functors::InternalInventory;
+use shikataganai_common::ecs::resources::light::{seed_light, RelightEvent};
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::networking::{ServerChannel, ServerMessage};
+use shikataganai_common::util::array::{ImmediateNeighbours, DDD};
+use shikataganai_common::util::random::{position_random, position_random_f64};
+use std::collections::VecDeque;
+
+const SALT_PICK: u64 = 1;
+const SALT_TICK: u64 = 2;
+const SALT_DROP: u64 = 3;
+// Updates that cascade further than this wait for the next tick
+const MAX_BLOCK_UPDATES: usize = 4096;
+// Server ticks until a block that couldn't pop off because nobody had room for its drops tries again
+const POP_RETRY_DELAY: u32 = 60;
+
+// The block at `changed` got replaced, `location` is one of its immediate neighbours or the block itself
+#[derive(Copy, Clone, Debug)]
+pub struct BlockUpdateEvent {
+  pub location: DDD,
+  pub changed: DDD,
+}
+
+impl BlockUpdateEvent {
+  pub fn around(changed: DDD) -> impl Iterator<Item = BlockUpdateEvent> {
+    std::iter::once(changed)
+      .chain(changed.immediate_neighbours())
+      .map(move |location| BlockUpdateEvent { location, changed })
+  }
+}
+
+// Call wherever a block gets replaced, next to the RelightEvent
+pub fn notify_neighbours(block_updates: &mut EventWriter<BlockUpdateEvent>, changed: DDD) {
+  block_updates.send_batch(BlockUpdateEvent::around(changed));
+}
+
+type PlayerInventories<'w, 's> =
+  Query<'w, 's, (&'static ClientId, &'static Transform, &'static mut InternalInventory), With<SpawnPoint>>;
+type BlockInventories<'w, 's> = Query<'w, 's, &'static InternalInventory, Without<SpawnPoint>>;
+
+// Applies all of the outcome or, when nobody in reach has room for what the popped blocks leave behind, none of it and
+// returns false
+fn apply_outcome(
+  outcome: TickOutcome,
+  now: u32,
+  commands: &mut Commands,
+  (game_world, registry): (&mut GameWorld, &BlockRegistry),
+  (server, relight): (&mut RenetServer, &mut EventWriter<RelightEvent>),
+  (scheduled_ticks, dirty_chunks, updates): (&mut ScheduledTicks, &mut DirtyChunks, &mut VecDeque<BlockUpdateEvent>),
+  (players, block_inventories): (&mut PlayerInventories, &BlockInventories),
+) -> bool {
+  let mut stacks = vec![];
+  for (location, changed) in outcome.changes.iter() {
+    if outcome.popped.contains(location)
+      && let Some(block) = game_world.get(*location)
+      && block.block != changed.block
+    {
+      let mut roll = 0;
+      stacks.extend(registry.get(block.block).roll_drops(registry, || {
+        roll += 1;
+        position_random_f64(now as u64, SALT_DROP + (roll << 8), *location)
+      }));
+      if block.entity != Entity::from_bits(0) && let Ok(inventory) = block_inventories.get(block.entity) {
+        stacks.extend(inventory.inventory.iter().flatten().cloned());
+      }
+    }
+  }
+  if let Some(location) = outcome.popped.first() && !give_nearest_player(server, *location, players, stacks) {
+    return false;
+  }
+  for (location, delay) in outcome.schedule {
+    scheduled_ticks.schedule(location, now, delay);
+  }
+  for (location, changed) in outcome.changes {
+    if let Some(block) = game_world.get_mut(location) {
+      if block.block != changed.block {
+        if block.entity != Entity::from_bits(0) {
+          commands.entity(block.entity).despawn();
+        }
+        *block = changed;
+        block.entity = Entity::from_bits(0);
+        let definition = registry.get(block.block);
+        if definition.need_to_spawn_functors() {
+          definition.spawn_or_add_functors(block, location, commands);
+        }
+      } else {
+        block.meta = changed.meta;
+      }
+      let block_transfer = (*block).into();
+      seed_light(game_world, registry, location);
+      dirty_chunks.mark(location);
+      relight.send(RelightEvent::Relight(location));
+      updates.extend(BlockUpdateEvent::around(location));
+      server.broadcast_message(
+        ServerChannel::GameEvent.id(),
+        serialize(&ServerMessage::BlockPlace { location, block_transfer }).unwrap(),
+      );
+    }
+  }
+  true
+}
+
+pub fn block_ticks(
+  mut commands: Commands,
+  mut game_world: ResMut<GameWorld>,
+  (mut scheduled_ticks, mut server): (ResMut<ScheduledTicks>, ResMut<RenetServer>),
+  (mut relight, mut update_events): (EventWriter<RelightEvent>, EventReader<BlockUpdateEvent>),
+  (mut updates, mut dirty_chunks): (Local<VecDeque<BlockUpdateEvent>>, ResMut<DirtyChunks>),
+  (mut players, block_inventories): (PlayerInventories, BlockInventories),
+  (tick, random_tick_speed, registry): (Res<ServerTick>, Res<RandomTickSpeed>, Res<BlockRegistry>),
+) {
+  let now = tick.0;
+  updates.extend(update_events.iter().copied());
+  let mut ticks: Vec<(DDD, TickKind)> = scheduled_ticks
+    .take_due(now)
+    .into_iter()
+    .map(|location| (location, TickKind::Scheduled))
+    .collect();
+  for chunk in game_world.chunks.values() {
+    for (index, section) in chunk.sections.iter().enumerate() {
+      // Missing sections are all air, nothing in there ticks
+      if section.is_none() {
+        continue;
+      }
+      let bounds = section_bounds(chunk.coord, index);
+      for roll in 0..random_tick_speed.0 as u64 {
+        let picked = position_random(now as u64, SALT_PICK + (roll << 8), bounds.0);
+        let location = (
+          bounds.0 .0 + (picked % 16) as i32,
+          bounds.0 .1 + ((picked >> 16) % SECTION_HEIGHT as u64) as i32,
+          bounds.0 .2 + ((picked >> 32) % 16) as i32,
+        );
+        if game_world.get(location).is_some_and(|block| registry.get(block.block).ticks()) {
+          ticks.push((location, TickKind::Random));
+        }
+      }
+    }
+  }
+
+  for (location, kind) in ticks {
+    // Scheduled ticks of chunks that got unloaded in the meantime are dropped
+    let block = match game_world.get(location) {
+      None => continue,
+      Some(block) => *block,
+    };
+    let mut roll = 0;
+    let random = || {
+      roll += 1;
+      position_random_f64(now as u64, SALT_TICK + (roll << 8), location)
+    };
+    let outcome = registry
+      .get(block.block)
+      .on_tick(kind, block, location, game_world.as_ref(), registry.as_ref(), random);
+    let applied = apply_outcome(
+      outcome,
+      now,
+      &mut commands,
+      (game_world.as_mut(), registry.as_ref()),
+      (server.as_mut(), &mut relight),
+      (scheduled_ticks.as_mut(), dirty_chunks.as_mut(), &mut updates),
+      (&mut players, &block_inventories),
+    );
+    if !applied {
+      scheduled_ticks.schedule(location, now, POP_RETRY_DELAY);
+    }
+  }
+
+  let mut processed = 0;
+  while processed < MAX_BLOCK_UPDATES && let Some(update) = updates.pop_front() {
+    processed += 1;
+    let block = match game_world.get(update.location) {
+      None => continue,
+      Some(block) => *block,
+    };
+    let outcome = registry.get(block.block).on_neighbour_changed(
+      block,
+      update.location,
+      update.changed,
+      game_world.as_ref(),
+      registry.as_ref(),
+    );
+    let applied = apply_outcome(
+      outcome,
+      now,
+      &mut commands,
+      (game_world.as_mut(), registry.as_ref()),
+      (server.as_mut(), &mut relight),
+      (scheduled_ticks.as_mut(), dirty_chunks.as_mut(), &mut updates),
+      (&mut players, &block_inventories),
+    );
+    // Scheduled ticks of blocks that need support pop them off the same way
+    if !applied {
+      scheduled_ticks.schedule(update.location, now, POP_RETRY_DELAY);
+    }
+  }
+}
@@ -0,0 +1,136 @@
+use crate::ecs::plugins::server::ClientId;
+use crate::ecs::plugins::settings::{ChunkUnloadDelay, Generator, ViewDistance};
+use crate::ecs::resources::region::{encode_chunk, RegionStorage};
+use crate::ecs::resources::ticks::ScheduledTicks;
+use crate::ecs::resources::world::{ChunkWatchers, DirtyChunks, ServerGameWorld};
+use crate::ecs::systems::persistence::{chunk_functors, FunctorQuery};
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use shikataganai_common::ecs::components::blocks::registry::BlockRegistry;
+use shikataganai_common::ecs::resources::world::{ChunkState, GameWorld};
+use shikataganai_common::util::array::DD;
+use std::io::Result;
+
+const UNLOAD_CHECK_INTERVAL: f64 = 1.0;
+
+// Chunks waiting for their last save before they get dropped
+#[derive(Component)]
+pub struct UnloadTask {
+  pub task: Task<Result<()>>,
+  pub chunks: Vec<DD>,
+}
+
+pub fn unload_chunks(
+  mut commands: Commands,
+  (time, mut last_check): (Res<Time>, Local<f64>),
+  (view_distance, unload_delay): (Res<ViewDistance>, Res<ChunkUnloadDelay>),
+  mut game_world: ResMut<GameWorld>,
+  (mut chunk_watchers, mut dirty_chunks): (ResMut<ChunkWatchers>, ResMut<DirtyChunks>),
+  (storage, generator, registry): (Res<RegionStorage>, Res<Generator>, Res<BlockRegistry>),
+  (player_query, functor_query): (Query<(&ClientId, &Transform)>, FunctorQuery),
+) {
+  let now = time.elapsed_seconds_f64();
+  if now - *last_check < UNLOAD_CHECK_INTERVAL {
+    return;
+  }
+  *last_check = now;
+
+  let ChunkWatchers { sent, watchers, .. } = chunk_watchers.as_mut();
+  watchers.clear();
+  for (client, transform, sent_chunks) in player_query
+    .iter()
+    .filter_map(|(client, transform)| sent.get(&client.0).map(|sent_chunks| (client, transform, sent_chunks)))
+  {
+    let translation = transform.translation.floor();
+    let center = GameWorld::get_chunk_coord((translation.x as i32, translation.y as i32, translation.z as i32));
+    for chunk_coord in sent_chunks.iter() {
+      if (chunk_coord.0 - center.0).abs() > view_distance.0 || (chunk_coord.1 - center.1).abs() > view_distance.0 {
+        continue;
+      }
+      watchers.entry(*chunk_coord).or_default().insert(client.0);
+      // The client came back to a chunk it still holds, edits to it need the chunk loaded on the server again.
+      game_world.get_chunk_or_spawn(
+        *chunk_coord,
+        &mut commands,
+        None,
+        storage.as_ref(),
+        generator.as_ref(),
+        registry.as_ref(),
+      );
+    }
+  }
+
+  let mut unload = vec![];
+  for chunk_coord in game_world.chunks.keys() {
+    if chunk_watchers.watchers.contains_key(chunk_coord) {
+      chunk_watchers.unwatched_since.remove(chunk_coord);
+      continue;
+    }
+    if !matches!(game_world.chunk_state(*chunk_coord), Some(ChunkState::Loaded)) {
+      continue;
+    }
+    let since = *chunk_watchers.unwatched_since.entry(*chunk_coord).or_insert(now);
+    if now - since >= unload_delay.0.as_secs_f64() {
+      unload.push(*chunk_coord);
+    }
+  }
+  if unload.is_empty() {
+    return;
+  }
+
+  let mut encoded = vec![];
+  for chunk_coord in unload.iter() {
+    game_world.states.insert(*chunk_coord, ChunkState::Unloading);
+    chunk_watchers.unwatched_since.remove(chunk_coord);
+    if dirty_chunks.chunks.remove(chunk_coord) {
+      let chunk = &game_world.chunks[chunk_coord];
+      encoded.push((*chunk_coord, encode_chunk(chunk, chunk_functors(chunk, &functor_query))));
+    }
+  }
+  let storage = storage.clone();
+  commands.spawn(UnloadTask {
+    task: AsyncComputeTaskPool::get().spawn(async move { storage.write_chunks(encoded.into_iter()) }),
+    chunks: unload,
+  });
+}
+
+pub fn collect_unloaded_chunks(
+  mut commands: Commands,
+  mut query: Query<(Entity, &mut UnloadTask)>,
+  mut game_world: ResMut<GameWorld>,
+  mut dirty_chunks: ResMut<DirtyChunks>,
+  mut scheduled_ticks: ResMut<ScheduledTicks>,
+) {
+  for (e, mut task) in query.iter_mut() {
+    if let Some(result) = futures_lite::future::block_on(futures_lite::future::poll_once(&mut task.task)) {
+      commands.entity(e).despawn();
+      if let Err(err) = &result {
+        // Keep everything resident rather than lose changes, the next check tries again.
+        println!("Failed to save chunks before unloading: {}", err);
+      }
+      for chunk_coord in task.chunks.iter() {
+        if result.is_err() {
+          dirty_chunks.chunks.insert(*chunk_coord);
+        }
+        match game_world.chunk_state(*chunk_coord) {
+          // Changed while it was being written, its latest state isn't on disk.
+          Some(ChunkState::Unloading) if dirty_chunks.chunks.contains(chunk_coord) => {
+            game_world.states.insert(*chunk_coord, ChunkState::Loaded);
+          }
+          Some(ChunkState::Unloading) => {
+            scheduled_ticks.drop_chunk(*chunk_coord);
+            if let Some(chunk) = game_world.remove_chunk(*chunk_coord) {
+              chunk.foreach(|_, block| {
+                if block.entity != Entity::from_bits(0) {
+                  commands.entity(block.entity).despawn();
+                }
+              });
+            }
+          }
+          // Requested again while it was being written.
+          _ => {}
+        }
+      }
+    }
+  }
+}
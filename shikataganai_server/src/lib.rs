@@ -1,21 +1,32 @@
 #![feature(let_chains)]
 use bevy::app::ScheduleRunnerSettings;
+use bevy::log::LogPlugin;
 use bevy::prelude::*;
+use bevy::utils::tracing::dispatcher;
 use shikataganai_common::ecs::resources::world::GameWorld;
 use shikataganai_common::recipes::Recipes;
 use std::time::Duration;
 
 use crate::ecs::plugins::server::{ShikataganaiServerAddress, ShikataganaiServerPlugin};
+use crate::ecs::plugins::settings::ServerSettingsPlugin;
+use crate::ecs::resources::control::ServerControl;
 
 pub mod ecs;
 
-pub fn spawn_server(address: ShikataganaiServerAddress) {
-  App::new()
+pub fn spawn_server(address: ShikataganaiServerAddress, control: ServerControl) {
+  let mut app = App::new();
+  // Started from the client it logs through the client's subscriber, on its own it has to set up one
+  if !dispatcher::has_been_set() {
+    app.add_plugin(LogPlugin::default());
+  }
+  app
     .insert_resource(ScheduleRunnerSettings::run_loop(Duration::from_secs_f64(1.0 / 60.0)))
     .add_plugins(MinimalPlugins)
     .init_resource::<GameWorld>()
     .init_resource::<Recipes>()
     .insert_resource(address)
+    .insert_resource(control)
+    .add_plugin(ServerSettingsPlugin)
     .add_plugin(ShikataganaiServerPlugin)
     .run();
 }
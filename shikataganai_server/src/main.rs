@@ -1,7 +1,9 @@
 #![feature(let_chains)]
 use shikataganai_server::ecs::plugins::server::ShikataganaiServerAddress;
+use shikataganai_server::ecs::resources::control::ServerControl;
 use shikataganai_server::spawn_server;
 use std::env;
+use std::io::BufRead;
 
 fn main() {
   let address: Option<String> = env::args().into_iter().nth(1);
@@ -13,5 +15,25 @@ fn main() {
     Some(address) => ShikataganaiServerAddress { address },
   };
 
-  spawn_server(address);
+  let control = ServerControl::default();
+  {
+    let control = control.clone();
+    ctrlc::set_handler(move || control.request_shutdown()).unwrap();
+  }
+  {
+    let control = control.clone();
+    std::thread::spawn(move || {
+      for line in std::io::stdin().lock().lines() {
+        match line.as_ref().map(|line| line.trim()) {
+          Ok("save-all") => control.request_save_all(),
+          Ok("stop") => control.request_shutdown(),
+          Ok("") => {}
+          Ok(command) => println!("Unknown command {}, available: save-all, stop", command),
+          Err(_) => break,
+        }
+      }
+    });
+  }
+
+  spawn_server(address, control);
 }
@@ -0,0 +1,301 @@
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use shikataganai_common::ecs::components::blocks::block_id::BlockId;
+use shikataganai_common::ecs::components::blocks::registry::{load_definitions, BlockRegistry, BLOCK_DEFINITIONS_DIR};
+use shikataganai_common::ecs::components::chunk::{Chunk, SECTION_HEIGHT};
+use shikataganai_common::ecs::resources::world::GameWorld;
+use shikataganai_common::util::array::{DD, DDD};
+use shikataganai_common::util::random::hash_str;
+use shikataganai_common::worldgen::place_decorations;
+use shikataganai_server::ecs::plugins::settings::{GeneratorSettings, ServerSettings};
+use shikataganai_server::ecs::resources::region::SavedChunk;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
+const USAGE: &str = "Usage: shikataganai_worldgen_preview [options]
+  --config <path>       server config to take the generator from, shikataganai_server.toml by default
+  --seed <seed>         integer seed, anything else gets hashed into one
+  --generator <type>    perlin, superflat or void
+  --height <blocks>     world height
+  --from <x,z>          first chunk of the rectangle, -4,-4 by default
+  --to <x,z>            last chunk of the rectangle, 4,4 by default
+  --out <directory>     where the images go, worldgen_preview by default";
+
+struct Options {
+  config: PathBuf,
+  seed: Option<String>,
+  generator: Option<String>,
+  height: Option<i32>,
+  from: DD,
+  to: DD,
+  out: PathBuf,
+}
+
+fn fail(message: &str) -> ! {
+  println!("{}\n{}", message, USAGE);
+  std::process::exit(1)
+}
+
+fn parse_chunk_coord(value: &str) -> DD {
+  match value.split_once(',').map(|(x, z)| (x.trim().parse(), z.trim().parse())) {
+    Some((Ok(x), Ok(z))) => (x, z),
+    _ => fail(&format!("Bad chunk coordinates {}", value)),
+  }
+}
+
+fn parse_options() -> Options {
+  let mut options = Options {
+    config: PathBuf::from("shikataganai_server.toml"),
+    seed: None,
+    generator: None,
+    height: None,
+    from: (-4, -4),
+    to: (4, 4),
+    out: PathBuf::from("worldgen_preview"),
+  };
+  let mut args = std::env::args().skip(1);
+  while let Some(arg) = args.next() {
+    let value = args
+      .next()
+      .unwrap_or_else(|| fail(&format!("Missing value for {}", arg)));
+    match arg.as_str() {
+      "--config" => options.config = PathBuf::from(value),
+      "--seed" => options.seed = Some(value),
+      "--generator" => options.generator = Some(value),
+      "--height" => options.height = Some(value.parse().unwrap_or_else(|_| fail(&format!("Bad height {}", value)))),
+      "--from" => options.from = parse_chunk_coord(&value),
+      "--to" => options.to = parse_chunk_coord(&value),
+      "--out" => options.out = PathBuf::from(value),
+      _ => fail(&format!("Unknown option {}", arg)),
+    }
+  }
+  if options.from.0 > options.to.0 || options.from.1 > options.to.1 {
+    fail("--from has to be the lower corner of the rectangle");
+  }
+  options
+}
+
+// Settings from the config file with the command line on top
+fn load_settings(options: &Options) -> ServerSettings {
+  let mut settings: ServerSettings = match std::fs::read_to_string(&options.config) {
+    Ok(str) => toml::from_str(&str).unwrap_or_else(|err| fail(&format!("Bad config: {}", err))),
+    Err(_) => ServerSettings::default(),
+  };
+  if let Some(seed) = &options.seed {
+    settings.seed = Some(seed.parse().unwrap_or(hash_str(seed) as i64));
+  }
+  if let Some(generator) = &options.generator {
+    // Same as in the config, so superflat gets its default layers
+    settings.generator = toml::from_str::<GeneratorSettings>(&format!("type = \"{}\"", generator))
+      .unwrap_or_else(|_| fail(&format!("Unknown generator {}", generator)));
+  }
+  if let Some(height) = options.height {
+    settings.world_height = height;
+  }
+  settings
+}
+
+// Generates the chunks on every core, decorations spilling between chunks of the rectangle get placed like on the server
+fn generate(settings: &ServerSettings, registry: &BlockRegistry, from: DD, to: DD) -> HashMap<DD, Chunk> {
+  let generator = settings
+    .build_generator(registry)
+    .unwrap_or_else(|err| fail(&format!("Bad generator settings: {}", err)));
+  let coords: Vec<DD> = (from.0..=to.0)
+    .flat_map(|x| (from.1..=to.1).map(move |z| (x, z)))
+    .collect();
+  let next = AtomicUsize::new(0);
+  let generated = Mutex::new(HashMap::new());
+  let spilled = Mutex::new(vec![]);
+  let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+  std::thread::scope(|scope| {
+    for _ in 0..threads {
+      scope.spawn(|| loop {
+        let chunk_coord = match coords.get(next.fetch_add(1, Ordering::Relaxed)) {
+          None => break,
+          Some(chunk_coord) => *chunk_coord,
+        };
+        let (saved_chunk, blocks) = SavedChunk::generate(chunk_coord, generator.as_ref(), registry);
+        generated.lock().unwrap().insert(chunk_coord, saved_chunk.chunk);
+        spilled.lock().unwrap().extend(blocks);
+      });
+    }
+  });
+  let mut chunks = generated.into_inner().unwrap();
+  let mut by_chunk: HashMap<DD, Vec<(DDD, BlockId)>> = HashMap::new();
+  for (location, block) in spilled.into_inner().unwrap() {
+    by_chunk
+      .entry(GameWorld::get_chunk_coord(location))
+      .or_default()
+      .push((location, block));
+  }
+  for (chunk_coord, blocks) in by_chunk {
+    if let Some(chunk) = chunks.get_mut(&chunk_coord) {
+      place_decorations(chunk, blocks);
+    }
+  }
+  chunks
+}
+
+// Highest block of the column that isn't air
+fn top_block(chunk: &Chunk, x: i32, z: i32) -> Option<(i32, BlockId)> {
+  for (index, section) in chunk.sections.iter().enumerate().rev() {
+    if let Some(section) = section {
+      let bottom = index as i32 * SECTION_HEIGHT;
+      for y in (bottom..bottom + SECTION_HEIGHT).rev() {
+        let block = section.grid[(x, y, z)].block;
+        if block != BlockId::AIR {
+          return Some((y, block));
+        }
+      }
+    }
+  }
+  None
+}
+
+// Blocks from definition files get some colour made up from their name
+fn block_colour(block: BlockId, registry: &BlockRegistry) -> [u8; 3] {
+  match block {
+    BlockId::AIR => [0, 0, 0],
+    BlockId::DIRT => [134, 96, 67],
+    BlockId::GRASS => [91, 153, 52],
+    BlockId::COBBLE => [122, 122, 122],
+    BlockId::IRON => [216, 175, 147],
+    BlockId::STAIR => [160, 130, 90],
+    BlockId::CHEST => [150, 105, 50],
+    BlockId::FURNACE => [90, 90, 90],
+    block => {
+      let hash = hash_str(&registry.get(block).name);
+      [hash as u8, (hash >> 8) as u8, (hash >> 16) as u8]
+    }
+  }
+}
+
+fn main() {
+  let options = parse_options();
+  // Ids don't matter here, but the settings may name blocks from definition files
+  let registry = BlockRegistry::assign(load_definitions(Path::new(BLOCK_DEFINITIONS_DIR)), &[]);
+  let settings = load_settings(&options);
+  let (from, to) = (options.from, options.to);
+  println!(
+    "Generating chunks {:?} to {:?} with seed {} and world height {}",
+    from,
+    to,
+    settings.seed(),
+    settings.world_height()
+  );
+  let start = Instant::now();
+  let chunks = generate(&settings, &registry, from, to);
+  println!(
+    "Generated {} chunks in {:.2}s",
+    chunks.len(),
+    start.elapsed().as_secs_f64()
+  );
+
+  let width = ((to.0 - from.0 + 1) * 16) as u32;
+  let height = ((to.1 - from.1 + 1) * 16) as u32;
+  let origin = (from.0 * 16, from.1 * 16);
+  let pixel = |px: u32, pz: u32| (origin.0 + px as i32, origin.1 + pz as i32);
+  let chunk_at = |(x, z): DD| &chunks[&GameWorld::get_chunk_coord((x, 0, z))];
+
+  let tops: Vec<Option<(i32, BlockId)>> = (0..height)
+    .flat_map(|pz| (0..width).map(move |px| (px, pz)))
+    .map(|(px, pz)| {
+      let (x, z) = pixel(px, pz);
+      top_block(chunk_at((x, z)), x, z)
+    })
+    .collect();
+  let top = |px: u32, pz: u32| tops[(pz * width + px) as usize];
+  let (lowest, highest) = tops
+    .iter()
+    .flatten()
+    .fold((i32::MAX, i32::MIN), |(lowest, highest), (y, _)| {
+      (lowest.min(*y), highest.max(*y))
+    });
+
+  std::fs::create_dir_all(&options.out).unwrap();
+
+  // Brighter is higher, lit from the north-west so slopes stand out. Columns of nothing but air stay black.
+  let heightmap = GrayImage::from_fn(width, height, |px, pz| match top(px, pz) {
+    None => Luma([0]),
+    Some((y, _)) => {
+      let level = 48.0 + 207.0 * (y - lowest) as f64 / (highest - lowest).max(1) as f64;
+      let neighbour = |px: u32, pz: u32| top(px, pz).map_or(y, |(y, _)| y);
+      let slope = (y - neighbour(px.saturating_sub(1), pz)) + (y - neighbour(px, pz.saturating_sub(1)));
+      Luma([(level * (1.0 + 0.1 * slope as f64)).clamp(1.0, 255.0) as u8])
+    }
+  });
+  heightmap.save(options.out.join("height.png")).unwrap();
+  if lowest <= highest {
+    println!("Surface heights range from {} to {}", lowest, highest);
+  }
+
+  let surface = RgbImage::from_fn(width, height, |px, pz| {
+    Rgb(top(px, pz).map_or([0, 0, 0], |(_, block)| block_colour(block, &registry)))
+  });
+  surface.save(options.out.join("surface.png")).unwrap();
+
+  // One image per ore and section, brighter columns hold more of the ore
+  let mut ores: Vec<BlockId> = vec![];
+  for ore in settings.ores.iter() {
+    let block = ore
+      .resolve(&registry)
+      .unwrap_or_else(|err| fail(&format!("Bad ore settings: {}", err)))
+      .block;
+    if !ores.contains(&block) {
+      ores.push(block);
+    }
+  }
+  let sections = settings.world_height() / SECTION_HEIGHT;
+  for ore in ores {
+    for section in 0..sections {
+      let bottom = section * SECTION_HEIGHT;
+      let mut total = 0;
+      let mut solid = 0;
+      let counts: Vec<u32> = (0..height)
+        .flat_map(|pz| (0..width).map(move |px| (px, pz)))
+        .map(|(px, pz)| {
+          let (x, z) = pixel(px, pz);
+          let chunk = chunk_at((x, z));
+          let mut count = 0;
+          for y in bottom..bottom + SECTION_HEIGHT {
+            let block = chunk.get((x, y, z)).map_or(BlockId::AIR, |block| block.block);
+            if block == ore {
+              count += 1;
+            }
+            if block != BlockId::AIR {
+              solid += 1;
+            }
+          }
+          total += count;
+          count
+        })
+        .collect();
+      if total == 0 {
+        continue;
+      }
+      let density = GrayImage::from_fn(width, height, |px, pz| {
+        let count = counts[(pz * width + px) as usize];
+        Luma([(255.0 * (count as f64 / SECTION_HEIGHT as f64).sqrt()) as u8])
+      });
+      let name = &registry.get(ore).name;
+      density
+        .save(
+          options
+            .out
+            .join(format!("ore_{}_{}-{}.png", name, bottom, bottom + SECTION_HEIGHT - 1)),
+        )
+        .unwrap();
+      println!(
+        "{} at y {}-{}: {} blocks, {:.2}% of the solid blocks",
+        name,
+        bottom,
+        bottom + SECTION_HEIGHT - 1,
+        total,
+        100.0 * total as f64 / solid as f64
+      );
+    }
+  }
+  println!("Images written to {}", options.out.display());
+}